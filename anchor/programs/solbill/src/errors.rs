@@ -30,4 +30,118 @@ pub enum SolBillError {
     SubscriptionCompleted,
     #[msg("Invalid cranker token account — must be owned by the cranker")]
     InvalidCrankerTokenAccount,
+    #[msg("Invalid revenue split — shares must be non-zero and sum to 10000 bps")]
+    InvalidRevenueSplit,
+    #[msg("Invalid split recipient — must be a token account of the accepted mint")]
+    InvalidSplitRecipient,
+    #[msg("Revenue split account does not match the configured split")]
+    RevenueSplitMismatch,
+    #[msg("Invalid reward escalation — step amount and interval must be greater than zero")]
+    InvalidRewardEscalation,
+    #[msg("Reward pool account does not match the service's reward pool")]
+    RewardPoolMismatch,
+    #[msg("Insufficient reward pool balance")]
+    InsufficientPoolBalance,
+    #[msg("Payment is still collectable — subscription is not delinquent")]
+    PaymentStillCollectable,
+    #[msg("Only the merchant's collector may collect during the exclusivity window")]
+    ExclusiveCollectionWindow,
+    #[msg("Invalid exclusive window — must be non-negative and shorter than the interval")]
+    InvalidExclusiveWindow,
+    #[msg("Invalid batch — remaining accounts must be (subscription, plan, token account, treasury) tuples")]
+    InvalidBatchAccounts,
+    #[msg("Subscriber's token account cannot cover the charge")]
+    InsufficientFunds,
+    #[msg("Subscription needs extra accounts and must be collected with collect_payment")]
+    UnsupportedInBatch,
+    #[msg("Payment method mismatch — use the native SOL instructions for lamport plans")]
+    PaymentMethodMismatch,
+    #[msg("Native SOL billing is not enabled for this service")]
+    NativeSolNotEnabled,
+    #[msg("Transfer-hook mints must be collected with collect_payment")]
+    TransferHookUnsupported,
+    #[msg("Both plans must be priced in the same mint")]
+    PlanMintMismatch,
+    #[msg("Invalid price feed — wrong account, format or settings")]
+    InvalidPriceFeed,
+    #[msg("Price is too old")]
+    StalePrice,
+    #[msg("Price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Charge exceeds the subscriber's maximum token amount")]
+    MaxTokenAmountExceeded,
+    #[msg("Invalid spending caps — a per-period cap needs a period length")]
+    InvalidSpendingCaps,
+    #[msg("Charge exceeds the subscriber's per-charge cap")]
+    PerChargeCapExceeded,
+    #[msg("Charge exceeds the subscriber's per-period spending cap")]
+    PeriodCapExceeded,
+    #[msg("Charge exceeds the subscriber's lifetime spending cap")]
+    LifetimeCapExceeded,
+    #[msg("Charge exceeds the subscriber's approved allowance; it must be reapproved first")]
+    ChargeExceedsDelegation,
+    #[msg("Invalid late fee — basis points cannot exceed 10000")]
+    InvalidLateFee,
+    #[msg("Invalid coupon — check the discount, duration and eligible plans")]
+    InvalidCoupon,
+    #[msg("Coupon has expired")]
+    CouponExpired,
+    #[msg("Coupon has reached its redemption limit")]
+    CouponRedemptionLimitReached,
+    #[msg("Coupon cannot be redeemed on this plan")]
+    CouponNotEligible,
+    #[msg("Coupon has already been redeemed on this subscription")]
+    CouponAlreadyRedeemed,
+    #[msg("Subscription has redeemed the maximum number of coupons")]
+    TooManyCoupons,
+    #[msg("Subscription is already on this plan")]
+    SamePlan,
+    #[msg("Offer signature missing or not made by the service authority")]
+    InvalidOfferSignature,
+    #[msg("Offer does not match this service, plan or subscriber")]
+    OfferMismatch,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Invalid affiliate — reward must be 1-10000 bps over at least one collection")]
+    InvalidAffiliate,
+    #[msg("Affiliate accounts do not match the subscription's referral")]
+    AffiliateMismatch,
+    #[msg("Subscribers cannot refer themselves")]
+    SelfReferral,
+    #[msg("Affiliate rewards are paid in the service's mint; this plan is priced in another")]
+    AffiliateMintMismatch,
+    #[msg("Invalid loyalty tiers — at most 4, increasing payment counts, 1-10000 bps")]
+    InvalidLoyaltyTiers,
+    #[msg("Invalid price phases — at most 4, each with an amount and interval; only the last may be open-ended")]
+    InvalidPricePhases,
+    #[msg("Price increased — the subscriber must re-approve the delegation")]
+    ReapprovalRequired,
+    #[msg("Invalid amount range — floor must be positive and no higher than the ceiling")]
+    InvalidAmountRange,
+    #[msg("Chosen amount is outside the plan's range, or the plan has a fixed price")]
+    AmountOutOfRange,
+    #[msg("Pay-what-you-want pricing cannot be combined with price phases or a private offer")]
+    PricingModeConflict,
+    #[msg("Free subscriptions are never charged")]
+    FreeSubscription,
+    #[msg("Invalid seat limits — minimum must be at least 1 and no higher than the maximum")]
+    InvalidSeatLimits,
+    #[msg("Quantity is outside the plan's seat limits")]
+    InvalidQuantity,
+    #[msg("Every seat is already assigned")]
+    SeatLimitReached,
+    #[msg("Remove members before reducing seats below those assigned")]
+    SeatsInUse,
+    #[msg("Wallet has no access through this subscription")]
+    AccessDenied,
+    #[msg("Every assigned seat must be passed in to be closed with the subscription")]
+    SeatsNotReleased,
+    #[msg("Add-on must be an active, flat-priced plan of the same service and mint")]
+    InvalidSubscriptionItem,
+    #[msg("Subscription already has the maximum number of add-ons")]
+    TooManyItems,
+    #[msg("Every attached add-on must be passed in to be closed with the subscription")]
+    ItemsNotReleased,
+    #[msg("Token account is frozen")]
+    TokenAccountFrozen,
 }
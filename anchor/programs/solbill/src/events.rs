@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::state::LineItem;
+
+/// Receipt for a successful recurring collection.
+#[event]
+pub struct PaymentCollected {
+    pub subscription: Pubkey,
+    pub subscriber: Pubkey,
+    pub plan: Pubkey,
+    /// Total charged to the subscriber.
+    pub amount: u64,
+    /// Token reward carved out of the charge for the cranker.
+    pub crank_reward: u64,
+    /// Lamport reward paid to the cranker from the service's reward pool.
+    pub pool_reward: u64,
+    /// Token-2022 transfer fee withheld by the mint. Added on top of `amount`
+    /// when the plan makes the subscriber pay it.
+    pub transfer_fee: u64,
+    /// Portion of `amount` made up of one-off charges.
+    pub one_off_charges: u64,
+    /// Add-ons charged with the recurring price, each part of `amount`.
+    pub items: Vec<LineItem>,
+    /// Portion of `amount` charged as a late fee.
+    pub late_fee: u64,
+    /// Portion of `amount` paid to the referring affiliate.
+    pub affiliate_reward: u64,
+    /// Recurring price charged after loyalty and coupon discounts, in plan units.
+    pub effective_price: u64,
+    /// Loyalty discount applied for the subscriber's tenure, in basis points.
+    pub loyalty_discount_bps: u16,
+    /// Seats billed.
+    pub quantity: u32,
+    /// Seat-removal credit taken off the recurring charge.
+    pub credit_applied: u64,
+    pub payments_made: u32,
+    pub next_billing_timestamp: i64,
+}
+
+/// A one-off charge queued for a subscription's next collection.
+#[event]
+pub struct OneOffChargeAdded {
+    pub subscription: Pubkey,
+    pub amount: u64,
+    /// Total one-off charges now awaiting collection.
+    pub pending_charges: u64,
+}
+
+/// A subscriber changed the number of seats on a subscription.
+#[event]
+pub struct QuantityUpdated {
+    pub subscription: Pubkey,
+    pub quantity: u32,
+    /// Prorated charge for added seats, queued for the next collection.
+    pub prorated_charge: u64,
+    /// Prorated credit for removed seats.
+    pub prorated_credit: u64,
+}
+
+/// A subscription in a batch that was left untouched.
+#[event]
+pub struct PaymentSkipped {
+    pub subscription: Pubkey,
+    /// `SolBillError` code explaining why the subscription was skipped.
+    pub error_code: u32,
+}
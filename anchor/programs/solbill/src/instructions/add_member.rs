@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{SeatAssignment, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct AddMember<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        constraint = subscription.has_access() @ SolBillError::SubscriptionNotActive,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// CHECK: The wallet given the seat; any address may be assigned.
+    pub member: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + SeatAssignment::INIT_SPACE,
+        seeds = [b"seat", subscription.key().as_ref(), member.key().as_ref()],
+        bump,
+    )]
+    pub seat_assignment: Account<'info, SeatAssignment>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddMember>) -> Result<()> {
+    let subscription = &mut ctx.accounts.subscription;
+    require!(
+        subscription.assigned_seats < subscription.quantity,
+        SolBillError::SeatLimitReached
+    );
+    subscription.assigned_seats += 1;
+
+    let seat = &mut ctx.accounts.seat_assignment;
+    seat.subscription = subscription.key();
+    seat.member = ctx.accounts.member.key();
+    seat.assigned_at = Clock::get()?.unix_timestamp;
+    seat.generation = subscription.generation;
+    seat.bump = ctx.bumps.seat_assignment;
+
+    msg!(
+        "Seat assigned to {} ({}/{} seats)",
+        seat.member,
+        subscription.assigned_seats,
+        subscription.quantity,
+    );
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::errors::SolBillError;
+use crate::events::OneOffChargeAdded;
+use crate::state::{ServiceAccount, SubscriptionAccount, SubscriptionStatus};
+
+#[derive(Accounts)]
+pub struct AddOneOffCharge<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = service,
+        constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+        constraint = !subscription.is_free() @ SolBillError::FreeSubscription,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscriber's token account, whose allowance must cover the charge.
+    #[account(
+        address = subscription.subscriber_token_account,
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+pub fn handler(ctx: Context<AddOneOffCharge>, amount: u64) -> Result<()> {
+    require!(amount > 0, SolBillError::InvalidAmount);
+
+    let subscription_key = ctx.accounts.subscription.key();
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.pending_charges = subscription
+        .pending_charges
+        .checked_add(amount)
+        .ok_or(SolBillError::Overflow)?;
+
+    // Only a flat-priced charge is known up front; quote-priced ones are
+    // re-checked against the caps at collection. The subscriber must have
+    // approved enough to pay it, or it could never be collected
+    if subscription.quote_pricing.is_none() {
+        let next_charge = subscription.next_charge()?;
+        subscription.check_max_token_amount(next_charge)?;
+        subscription.spending_caps.admits(next_charge)?;
+        let token_account = &ctx.accounts.subscriber_token_account;
+        require!(
+            token_account.delegate.contains(&subscription_key)
+                && token_account.delegated_amount >= next_charge,
+            SolBillError::ChargeExceedsDelegation
+        );
+    }
+
+    msg!(
+        "One-off charge of {} added to {} ({} pending)",
+        amount,
+        subscription_key,
+        subscription.pending_charges,
+    );
+    emit!(OneOffChargeAdded {
+        subscription: subscription_key,
+        amount,
+        pending_charges: subscription.pending_charges,
+    });
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::SolBillError;
+use crate::instructions::utils::approve_delegation;
+use crate::state::{
+    LineItem, PlanAccount, RevenueSplit, ServiceAccount, SubscriptionAccount, SubscriptionItem,
+    SubscriptionStatus, MAX_LINE_ITEMS,
+};
+
+#[derive(Accounts)]
+pub struct AttachItem<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        has_one = service,
+        constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+        constraint = !subscription.is_free() @ SolBillError::FreeSubscription,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscription's current plan (resolves the revenue split).
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    /// The add-on plan, billed in tokens on the subscription's schedule.
+    #[account(
+        seeds = [b"plan", service.key().as_ref(), addon_plan.plan_index.to_le_bytes().as_ref()],
+        bump = addon_plan.bump,
+        has_one = service,
+        constraint = addon_plan.is_active @ SolBillError::PlanNotActive,
+        constraint = addon_plan.key() != subscription.plan
+            && !addon_plan.native_sol
+            && addon_plan.quote_pricing.is_none()
+            && addon_plan.amount > 0
+            && addon_plan.mint_for(&service) == accepted_mint.key() @ SolBillError::InvalidSubscriptionItem,
+    )]
+    pub addon_plan: Account<'info, PlanAccount>,
+
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + SubscriptionItem::INIT_SPACE,
+        seeds = [b"item", subscription.key().as_ref(), addon_plan.key().as_ref()],
+        bump,
+    )]
+    pub item: Account<'info, SubscriptionItem>,
+
+    /// The subscriber's token account.
+    #[account(
+        mut,
+        address = subscription.subscriber_token_account,
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The subscription's mint (checked for a transfer fee).
+    #[account(
+        address = subscriber_token_account.mint,
+    )]
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// The revenue split configured on the plan or service, if any; the
+    /// allowance covers one transfer per recipient.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+}
+
+pub fn handler(ctx: Context<AttachItem>) -> Result<()> {
+    let addon_plan = &ctx.accounts.addon_plan;
+    let subscription = &mut ctx.accounts.subscription;
+    require!(
+        subscription.items.len() < MAX_LINE_ITEMS,
+        SolBillError::TooManyItems
+    );
+
+    // The add-on is billed from the next charge, at the add-on plan's price
+    subscription.items.push(LineItem {
+        plan: addon_plan.key(),
+        amount: addon_plan.amount,
+    });
+    if subscription.quote_pricing.is_none() {
+        let recurring = subscription.recurring_amount()?;
+        let next_price = subscription
+            .discounted(recurring)?
+            .checked_add(subscription.items_amount()?)
+            .ok_or(SolBillError::Overflow)?;
+        subscription.spending_caps.admits(next_price)?;
+    }
+
+    let item = &mut ctx.accounts.item;
+    item.subscription = subscription.key();
+    item.plan = addon_plan.key();
+    item.amount = addon_plan.amount;
+    item.attached_at = Clock::get()?.unix_timestamp;
+    item.bump = ctx.bumps.item;
+
+    // Widen the delegation to cover the add-on
+    approve_delegation(
+        &ctx.accounts.service,
+        &ctx.accounts.plan,
+        &ctx.accounts.subscription,
+        ctx.accounts.revenue_split.as_ref(),
+        &ctx.accounts.subscriber,
+        &ctx.accounts.subscriber_token_account,
+        &ctx.accounts.accepted_mint,
+        &ctx.accounts.token_program,
+    )?;
+
+    msg!(
+        "Add-on plan {} attached ({} per cycle)",
+        addon_plan.plan_index,
+        addon_plan.amount,
+    );
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::instructions::utils::{close_subscription_dependents, transfer_from_escrow};
+use crate::state::{ServiceAccount, SubscriptionAccount, SubscriptionStatus};
+
+#[derive(Accounts)]
+pub struct CancelNativeSubscription<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        has_one = service,
+        close = subscriber,
+        constraint = subscription.status != SubscriptionStatus::Cancelled @ SolBillError::AlreadyCancelled,
+        constraint = subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscription's lamport escrow, refunded in full to the subscriber.
+    #[account(
+        mut,
+        seeds = [b"escrow", subscription.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: every assigned `SeatAssignment`, then every attached
+    // `SubscriptionItem`, closed alongside.
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CancelNativeSubscription<'info>>,
+) -> Result<()> {
+    close_subscription_dependents(
+        &ctx.accounts.subscription,
+        ctx.remaining_accounts,
+        &ctx.accounts.subscriber.to_account_info(),
+        true,
+    )?;
+
+    let subscription_key = ctx.accounts.subscription.key();
+    let refund = ctx.accounts.escrow.lamports();
+    transfer_from_escrow(
+        &ctx.accounts.system_program,
+        &ctx.accounts.escrow.to_account_info(),
+        &ctx.accounts.subscriber.to_account_info(),
+        refund,
+        &[&[b"escrow", subscription_key.as_ref(), &[ctx.bumps.escrow]]],
+    )?;
+
+    ctx.accounts.subscription.status = SubscriptionStatus::Cancelled;
+
+    let service = &mut ctx.accounts.service;
+    service.subscriber_count = service.subscriber_count.saturating_sub(1);
+
+    msg!(
+        "Native subscription cancelled: {} (escrow refund: {} lamports)",
+        ctx.accounts.subscriber.key(),
+        refund,
+    );
+    Ok(())
+}
@@ -2,6 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{revoke, Revoke, TokenAccount, TokenInterface};
 
 use crate::errors::SolBillError;
+use crate::instructions::utils::close_subscription_dependents;
 use crate::state::{ServiceAccount, SubscriptionAccount, SubscriptionStatus};
 
 #[derive(Accounts)]
@@ -23,6 +24,7 @@ pub struct CancelSubscription<'info> {
         has_one = service,
         close = subscriber,
         constraint = subscription.status != SubscriptionStatus::Cancelled @ SolBillError::AlreadyCancelled,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
     )]
     pub subscription: Account<'info, SubscriptionAccount>,
 
@@ -34,9 +36,18 @@ pub struct CancelSubscription<'info> {
     pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
 
     pub token_program: Interface<'info, TokenInterface>,
+    // remaining_accounts: every assigned `SeatAssignment`, then every attached
+    // `SubscriptionItem`, closed alongside.
 }
 
-pub fn handler(ctx: Context<CancelSubscription>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, CancelSubscription<'info>>) -> Result<()> {
+    close_subscription_dependents(
+        &ctx.accounts.subscription,
+        ctx.remaining_accounts,
+        &ctx.accounts.subscriber.to_account_info(),
+        true,
+    )?;
+
     let subscription = &mut ctx.accounts.subscription;
 
     // Set status to cancelled
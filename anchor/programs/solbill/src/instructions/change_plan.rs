@@ -1,8 +1,11 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{approve, revoke, Approve, Revoke, TokenAccount, TokenInterface};
+use anchor_spl::token_interface::{revoke, Mint, Revoke, TokenAccount, TokenInterface};
 
 use crate::errors::SolBillError;
-use crate::state::{PlanAccount, ServiceAccount, SubscriptionAccount, SubscriptionStatus};
+use crate::instructions::utils::approve_delegation;
+use crate::state::{
+    Coupon, PlanAccount, RevenueSplit, ServiceAccount, SubscriptionAccount, SubscriptionStatus,
+};
 
 #[derive(Accounts)]
 pub struct ChangePlan<'info> {
@@ -27,7 +30,10 @@ pub struct ChangePlan<'info> {
         seeds = [b"plan", service.key().as_ref(), new_plan.plan_index.to_le_bytes().as_ref()],
         bump = new_plan.bump,
         has_one = service,
+        constraint = new_plan.key() != old_plan.key() @ SolBillError::SamePlan,
         constraint = new_plan.is_active @ SolBillError::PlanNotActive,
+        constraint = !new_plan.native_sol @ SolBillError::PaymentMethodMismatch,
+        constraint = new_plan.mint_for(&service) == subscription.mint @ SolBillError::PlanMintMismatch,
     )]
     pub new_plan: Account<'info, PlanAccount>,
 
@@ -39,6 +45,7 @@ pub struct ChangePlan<'info> {
         has_one = service,
         constraint = subscription.plan == old_plan.key(),
         constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
     )]
     pub subscription: Account<'info, SubscriptionAccount>,
 
@@ -49,6 +56,12 @@ pub struct ChangePlan<'info> {
     )]
     pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
 
+    /// The accepted SPL token mint (checked for a transfer fee).
+    #[account(
+        address = new_plan.mint_for(&service),
+    )]
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
     /// CHECK: The subscription PDA used as delegate.
     #[account(
         seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
@@ -57,16 +70,100 @@ pub struct ChangePlan<'info> {
     pub delegate: AccountInfo<'info>,
 
     pub token_program: Interface<'info, TokenInterface>,
+
+    /// Coupon to redeem for the new plan, replacing any current discount.
+    #[account(
+        mut,
+        has_one = service,
+    )]
+    pub coupon: Option<Account<'info, Coupon>>,
+
+    /// The revenue split configured on the new plan or service, if any; the
+    /// allowance covers one transfer per recipient.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
 }
 
-pub fn handler(ctx: Context<ChangePlan>) -> Result<()> {
+pub fn handler(
+    ctx: Context<ChangePlan>,
+    max_token_amount: u64,
+    chosen_amount: Option<u64>,
+) -> Result<()> {
     let new_plan = &ctx.accounts.new_plan;
     let subscription = &mut ctx.accounts.subscription;
 
+    // Quote-priced plans are approved up to the subscriber's cap, so moving
+    // to one needs a cap like subscribing to one does
+    require!(
+        new_plan.quote_pricing.is_none() || max_token_amount > 0,
+        SolBillError::MaxTokenAmountExceeded
+    );
+
+    // Pay-what-you-want plans take the subscriber's amount within their range
+    // (defaults to the plan's floor)
+    if let Some(amount) = chosen_amount {
+        new_plan
+            .pay_what_you_want
+            .ok_or(SolBillError::AmountOutOfRange)?
+            .check(amount)?;
+    }
+    let amount = chosen_amount.unwrap_or(new_plan.amount);
+
+    if let Some(coupon) = ctx.accounts.coupon.as_mut() {
+        subscription.redeem_coupon(
+            coupon.key(),
+            coupon,
+            &new_plan.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+    }
+
+    // Seats carry over, so they must fit the new plan's limits
+    new_plan.check_quantity(subscription.quantity)?;
+
+    // The subscriber's caps must allow the new price (quote-priced plans are
+    // bounded by `max_token_amount` and checked at each charge instead)
+    if new_plan.quote_pricing.is_none() {
+        let new_price = amount
+            .checked_mul(subscription.quantity as u64)
+            .ok_or(SolBillError::Overflow)?;
+        subscription
+            .spending_caps
+            .admits(subscription.discounted(new_price)?)?;
+    }
+
     // Update subscription to new plan terms (effective next cycle)
+    let was_free = subscription.is_free();
     subscription.plan = new_plan.key();
-    subscription.amount = new_plan.amount;
+    subscription.treasury = new_plan.treasury_for(&ctx.accounts.service);
+    subscription.amount = amount;
+    subscription.crank_reward = new_plan.crank_reward;
+    subscription.percentage_reward = new_plan.percentage_reward;
+    subscription.reward_escalation = new_plan.reward_escalation;
     subscription.interval = new_plan.interval;
+    subscription.transfer_fee_mode = new_plan.transfer_fee_mode;
+    subscription.quote_pricing = new_plan.quote_pricing;
+    subscription.max_token_amount = max_token_amount;
+    subscription.late_fee = new_plan.late_fee;
+    subscription.loyalty_tiers = new_plan.loyalty_tiers.clone();
+    // Start the new plan's pricing schedule from its first phase; the
+    // delegation approved below covers it
+    subscription.price_phases = new_plan.price_phases.clone();
+    subscription.reapproval_required = false;
+    if !subscription.price_phases.is_empty() {
+        subscription.enter_phase(0);
+    }
+
+    // Upgrading from a free tier starts billing right away; moving to one stops
+    // it, so add-ons billed with the recurring charge must be detached first
+    if subscription.is_free() {
+        require!(
+            subscription.items.is_empty(),
+            SolBillError::FreeSubscription
+        );
+        subscription.next_billing_timestamp = i64::MAX;
+    } else if was_free {
+        subscription.next_billing_timestamp = Clock::get()?.unix_timestamp;
+    }
 
     // Revoke old approval and set new one for the new amount
     revoke(CpiContext::new(
@@ -77,23 +174,22 @@ pub fn handler(ctx: Context<ChangePlan>) -> Result<()> {
         },
     ))?;
 
-    approve(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Approve {
-                to: ctx.accounts.subscriber_token_account.to_account_info(),
-                delegate: ctx.accounts.delegate.to_account_info(),
-                authority: ctx.accounts.subscriber.to_account_info(),
-            },
-        ),
-        new_plan.amount,
+    approve_delegation(
+        &ctx.accounts.service,
+        &ctx.accounts.new_plan,
+        &ctx.accounts.subscription,
+        ctx.accounts.revenue_split.as_ref(),
+        &ctx.accounts.subscriber,
+        &ctx.accounts.subscriber_token_account,
+        &ctx.accounts.accepted_mint,
+        &ctx.accounts.token_program,
     )?;
 
     msg!(
         "Subscription plan changed: {} → plan {} ({} tokens/{}s)",
-        subscription.subscriber,
+        ctx.accounts.subscriber.key(),
         new_plan.plan_index,
-        new_plan.amount,
+        amount,
         new_plan.interval,
     );
     Ok(())
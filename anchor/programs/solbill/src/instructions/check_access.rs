@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{SeatAssignment, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct CheckAccess<'info> {
+    /// CHECK: The wallet whose access is checked (the subscriber or a member).
+    pub wallet: UncheckedAccount<'info>,
+
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The wallet's seat on the subscription (members only).
+    #[account(
+        seeds = [b"seat", subscription.key().as_ref(), wallet.key().as_ref()],
+        bump = seat_assignment.bump,
+        has_one = subscription,
+        constraint = seat_assignment.generation == subscription.generation @ SolBillError::AccessDenied,
+    )]
+    pub seat_assignment: Option<Account<'info, SeatAssignment>>,
+}
+
+pub fn handler(ctx: Context<CheckAccess>) -> Result<()> {
+    let subscription = &ctx.accounts.subscription;
+    let wallet = ctx.accounts.wallet.key();
+
+    // Members inherit the owner's access rather than holding their own subscription
+    require!(
+        wallet == subscription.subscriber || ctx.accounts.seat_assignment.is_some(),
+        SolBillError::AccessDenied
+    );
+    require!(subscription.has_access(), SolBillError::AccessDenied);
+
+    msg!("Access granted: {} via {}", wallet, subscription.key());
+    Ok(())
+}
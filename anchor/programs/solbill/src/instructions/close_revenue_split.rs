@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, RevenueSplit, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct CloseRevenueSplit<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The plan the split is attached to. Omit for a service-wide split.
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Option<Account<'info, PlanAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"split", revenue_split.target.as_ref()],
+        bump = revenue_split.bump,
+        has_one = service,
+        close = authority,
+        constraint = revenue_split.target
+            == plan.as_ref().map(|p| p.key()).unwrap_or(service.key())
+            @ SolBillError::RevenueSplitMismatch,
+    )]
+    pub revenue_split: Account<'info, RevenueSplit>,
+}
+
+pub fn handler(ctx: Context<CloseRevenueSplit>) -> Result<()> {
+    match ctx.accounts.plan.as_mut() {
+        Some(plan) => plan.revenue_split = None,
+        None => ctx.accounts.service.revenue_split = None,
+    }
+
+    msg!(
+        "Revenue split removed from {}",
+        ctx.accounts.revenue_split.target
+    );
+    Ok(())
+}
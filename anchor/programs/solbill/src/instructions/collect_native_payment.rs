@@ -0,0 +1,168 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::events::PaymentCollected;
+use crate::instructions::utils::{
+    escrow_available_lamports, pay_from_reward_pool, transfer_from_escrow,
+};
+use crate::state::{PlanAccount, RewardPool, ServiceAccount, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct CollectNativePayment<'info> {
+    /// The public crank turner who triggers the payment and receives the reward.
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = service,
+        constraint = subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscription's current plan (used for the exclusivity window).
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    /// The subscription's lamport escrow (source of funds).
+    #[account(
+        mut,
+        seeds = [b"escrow", subscription.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    /// The merchant's wallet receiving lamport payments.
+    #[account(
+        mut,
+        constraint = service.sol_treasury == Some(sol_treasury.key()) @ SolBillError::NativeSolNotEnabled,
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// The service's lamport reward pool, required when one is configured.
+    #[account(
+        mut,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump = reward_pool.bump,
+        has_one = service,
+    )]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+}
+
+pub fn handler(ctx: Context<CollectNativePayment>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        ctx.accounts.service.reward_pool == ctx.accounts.reward_pool.as_ref().map(|p| p.key()),
+        SolBillError::RewardPoolMismatch
+    );
+
+    let exclusive = ctx.accounts.plan.in_exclusive_window(
+        ctx.accounts.subscription.next_billing_timestamp,
+        clock.unix_timestamp,
+    );
+    if exclusive {
+        require!(
+            ctx.accounts
+                .service
+                .is_merchant_collector(&ctx.accounts.cranker.key()),
+            SolBillError::ExclusiveCollectionWindow
+        );
+    }
+
+    let subscription = &ctx.accounts.subscription;
+    subscription.check_collectable(clock.unix_timestamp)?;
+    let amount = subscription.effective_amount()?;
+    let loyalty_discount_bps = subscription.loyalty_discount_bps();
+    let late_fee = subscription.late_fee_for(amount)?;
+    let charge = amount.checked_add(late_fee).ok_or(SolBillError::Overflow)?;
+    subscription.check_spending_caps(charge, clock.unix_timestamp)?;
+    require!(
+        escrow_available_lamports(&ctx.accounts.escrow)? >= charge,
+        SolBillError::InsufficientFunds
+    );
+
+    let mut pool_reward = 0;
+    if let Some(pool) = ctx.accounts.reward_pool.as_mut().filter(|_| !exclusive) {
+        let lamports = pool.lamports_per_crank;
+        if pay_from_reward_pool(pool, &ctx.accounts.cranker.to_account_info(), lamports)? {
+            pool_reward = lamports;
+        } else {
+            msg!("Reward pool empty, falling back to escrow reward");
+        }
+    }
+
+    // The crank reward is carved out of the lamport payment itself
+    let subscription = &ctx.accounts.subscription;
+    let crank_reward = if exclusive || pool_reward > 0 {
+        0
+    } else {
+        subscription.crank_reward_for(amount, clock.unix_timestamp)?
+    };
+    let treasury_amount = charge
+        .checked_sub(crank_reward)
+        .ok_or(SolBillError::Overflow)?;
+
+    let subscription_key = subscription.key();
+    let escrow_bump = ctx.bumps.escrow;
+    let signer_seeds: &[&[&[u8]]] = &[&[b"escrow", subscription_key.as_ref(), &[escrow_bump]]];
+    let escrow = ctx.accounts.escrow.to_account_info();
+
+    transfer_from_escrow(
+        &ctx.accounts.system_program,
+        &escrow,
+        &ctx.accounts.cranker.to_account_info(),
+        crank_reward,
+        signer_seeds,
+    )?;
+    transfer_from_escrow(
+        &ctx.accounts.system_program,
+        &escrow,
+        &ctx.accounts.sol_treasury.to_account_info(),
+        treasury_amount,
+        signer_seeds,
+    )?;
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.record_payment(charge, clock.unix_timestamp)?;
+
+    msg!(
+        "Native collection success: Cranker Reward: {} (+{} lamports), Treasury: {}, Next billing: {}",
+        crank_reward,
+        pool_reward,
+        treasury_amount,
+        subscription.next_billing_timestamp,
+    );
+    emit!(PaymentCollected {
+        subscription: subscription_key,
+        subscriber: subscription.subscriber,
+        plan: subscription.plan,
+        amount: charge,
+        crank_reward,
+        pool_reward,
+        transfer_fee: 0,
+        one_off_charges: 0,
+        items: Vec::new(),
+        late_fee,
+        affiliate_reward: 0,
+        effective_price: amount,
+        loyalty_discount_bps,
+        quantity: subscription.quantity,
+        credit_applied: 0,
+        payments_made: subscription.payments_made,
+        next_billing_timestamp: subscription.next_billing_timestamp,
+    });
+    Ok(())
+}
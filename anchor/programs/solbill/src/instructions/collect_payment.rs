@@ -2,7 +2,14 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 use crate::errors::SolBillError;
-use crate::state::{ServiceAccount, SubscriptionAccount, SubscriptionStatus};
+use crate::events::PaymentCollected;
+use crate::instructions::utils::{
+    can_receive, pay_affiliate, pay_from_reward_pool, read_quote_price, split_remaining_accounts,
+    validate_revenue_split, TransferFeeSchedule,
+};
+use crate::state::{
+    Affiliate, PlanAccount, RevenueSplit, RewardPool, ServiceAccount, SubscriptionAccount,
+};
 
 #[derive(Accounts)]
 pub struct CollectPayment<'info> {
@@ -21,9 +28,16 @@ pub struct CollectPayment<'info> {
         seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
         bump = subscription.bump,
         has_one = service,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
     )]
     pub subscription: Account<'info, SubscriptionAccount>,
 
+    /// The subscription's current plan (used to resolve the revenue split).
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
     /// The subscriber's token account (source of funds).
     #[account(
         mut,
@@ -34,7 +48,7 @@ pub struct CollectPayment<'info> {
     /// The merchant's treasury token account (destination for main payment).
     #[account(
         mut,
-        address = service.treasury,
+        address = subscription.treasury,
     )]
     pub treasury: InterfaceAccount<'info, TokenAccount>,
 
@@ -46,47 +60,210 @@ pub struct CollectPayment<'info> {
     )]
     pub cranker_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    /// The accepted SPL token mint.
+    /// The SPL token mint the subscription pays in.
     #[account(
-        address = service.accepted_mint,
+        address = subscription.mint,
     )]
     pub accepted_mint: InterfaceAccount<'info, Mint>,
 
     pub token_program: Interface<'info, TokenInterface>,
+
+    /// The revenue split configured on the plan or service, if any.
+    /// Its recipients' token accounts are passed as remaining accounts.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+    // remaining_accounts: split payees, then the transfer-hook program, its
+    // extra-account-metas account and extra accounts (hook mints only).
+    /// The service's lamport reward pool, required when one is configured.
+    #[account(
+        mut,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump = reward_pool.bump,
+        has_one = service,
+    )]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+
+    /// CHECK: The price account locked into a quote-priced subscription;
+    /// matched against `subscription.quote_pricing` and parsed by the price reader.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    /// The referring affiliate, required while the subscription's referral pays out.
+    #[account(mut)]
+    pub affiliate: Option<Account<'info, Affiliate>>,
+
+    /// CHECK: The affiliate's payout token account; matched against the
+    /// referral and paid only while it can receive the subscription's mint.
+    #[account(mut)]
+    pub affiliate_token_account: Option<UncheckedAccount<'info>>,
 }
 
-pub fn handler(ctx: Context<CollectPayment>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, CollectPayment<'info>>) -> Result<()> {
     let clock = Clock::get()?;
 
+    validate_revenue_split(
+        &ctx.accounts.service,
+        &ctx.accounts.plan,
+        &ctx.accounts.subscription.mint,
+        ctx.accounts.revenue_split.as_ref(),
+    )?;
+    let (payees, hook_accounts) = split_remaining_accounts(
+        ctx.remaining_accounts,
+        ctx.accounts.revenue_split.as_deref(),
+    )?;
+    require!(
+        ctx.accounts.service.reward_pool == ctx.accounts.reward_pool.as_ref().map(|p| p.key()),
+        SolBillError::RewardPoolMismatch
+    );
+
+    // During the plan's exclusivity window only the merchant (or its designated
+    // collector) may collect, and such collections pay no reward.
+    let exclusive = ctx.accounts.plan.in_exclusive_window(
+        ctx.accounts.subscription.next_billing_timestamp,
+        clock.unix_timestamp,
+    );
+    if exclusive {
+        require!(
+            ctx.accounts
+                .service
+                .is_merchant_collector(&ctx.accounts.cranker.key()),
+            SolBillError::ExclusiveCollectionWindow
+        );
+    }
+
+    // Pay the cranker from the reward pool when it can cover the reward;
+    // otherwise fall back to the token reward carved out of the payment.
+    let mut pool_reward = 0;
+    if let Some(pool) = ctx.accounts.reward_pool.as_mut().filter(|_| !exclusive) {
+        let lamports = pool.lamports_per_crank;
+        if pay_from_reward_pool(pool, &ctx.accounts.cranker.to_account_info(), lamports)? {
+            pool_reward = lamports;
+        } else {
+            msg!("Reward pool empty, falling back to token reward");
+        }
+    }
+
+    let charge;
+    let debit;
+    let one_off_charges;
+    let items;
+    let late_fee;
+    let crank_reward;
+    let affiliate_reward;
+    let effective_price;
+    let loyalty_discount_bps;
+    let credit_applied;
+    let transfer_fee;
+
     // We access data immutably first for guards and transfer
     {
         let subscription = &ctx.accounts.subscription;
 
         // --- Guards ---
+        subscription.check_collectable(clock.unix_timestamp)?;
 
-        // Must be active or past due
-        require!(
-            subscription.status == SubscriptionStatus::Active
-                || subscription.status == SubscriptionStatus::PastDue,
-            SolBillError::SubscriptionNotActive,
-        );
+        // Quote-priced amounts are converted at the current price
+        let price = read_quote_price(
+            subscription.quote_pricing.as_ref(),
+            ctx.accounts.price_feed.as_deref(),
+            clock.unix_timestamp,
+        )?;
+        let decimals = ctx.accounts.accepted_mint.decimals;
+        let amount = subscription.effective_amount()?;
+        effective_price = amount;
+        loyalty_discount_bps = subscription.loyalty_discount_bps();
+        let recurring = subscription.to_token_amount(amount, price.as_ref(), decimals)?;
+        // Credit from removed seats comes off the recurring charge
+        credit_applied = subscription.credit_balance.min(recurring);
+        let recurring = recurring - credit_applied;
+        // Add-ons, merchant one-off charges and any late fee ride along with
+        // the recurring charge
+        items = subscription.items.clone();
+        let items_amount = subscription.items_amount()?;
+        one_off_charges = subscription.pending_charges;
+        late_fee = subscription.to_token_amount(
+            subscription.late_fee_for(amount)?,
+            price.as_ref(),
+            decimals,
+        )?;
+        charge = recurring
+            .checked_add(items_amount)
+            .and_then(|total| total.checked_add(one_off_charges))
+            .and_then(|total| total.checked_add(late_fee))
+            .ok_or(SolBillError::Overflow)?;
 
-        // Timing enforcement: cannot bill before due date
-        require!(
-            clock.unix_timestamp >= subscription.next_billing_timestamp,
-            SolBillError::BillingNotDue,
-        );
+        // --- Transfer Logic ---
+        let fee_schedule =
+            TransferFeeSchedule::load(&ctx.accounts.accepted_mint.to_account_info())?;
+        let reward = if exclusive || pool_reward > 0 {
+            0
+        } else {
+            let reward = subscription.crank_reward_for(amount, clock.unix_timestamp)?;
+            subscription
+                .to_token_amount(reward, price.as_ref(), decimals)?
+                .min(recurring)
+        };
 
-        // Check for max billing cycles limit BEFORE collecting
-        if subscription.max_billing_cycles > 0 {
-            if subscription.payments_made >= subscription.max_billing_cycles as u32 {
-                // This should not happen if status is correctly managed,
-                // but as a safety guard against race conditions or manual errors.
-                return err!(SolBillError::SubscriptionCompleted);
+        // The referring affiliate's share comes out of the merchant's portion
+        affiliate_reward = match &subscription.referral {
+            Some(referral) => {
+                require!(
+                    ctx.accounts.affiliate.as_ref().map(|a| a.key()) == Some(referral.affiliate)
+                        && ctx
+                            .accounts
+                            .affiliate_token_account
+                            .as_ref()
+                            .map(|a| a.key())
+                            == Some(referral.payout_token_account),
+                    SolBillError::AffiliateMismatch
+                );
+                // A closed, frozen or wrong-mint payout account would fail the
+                // whole collection, so the merchant keeps this reward instead
+                // and the payout is left for a later collection
+                let payable =
+                    ctx.accounts
+                        .affiliate_token_account
+                        .as_ref()
+                        .is_some_and(|account| {
+                            can_receive(
+                                account,
+                                &ctx.accounts.accepted_mint.key(),
+                                ctx.accounts.token_program.key,
+                            )
+                        });
+                if payable {
+                    referral
+                        .reward_for(recurring)?
+                        .min(recurring.saturating_sub(reward))
+                } else {
+                    msg!("Affiliate payout account can't receive, reward deferred");
+                    0
+                }
             }
-        }
+            None => 0,
+        };
+
+        // The cranker's leg is grossed up for the mint's transfer fee, so a
+        // heavily discounted charge may not carry the whole reward
+        crank_reward = fee_schedule.fit_reward(
+            reward,
+            charge - affiliate_reward,
+            subscription.transfer_fee_mode,
+        )?;
+
+        // Caps bound what leaves the subscriber's wallet, so they count the
+        // transfer fee the subscriber bears on each leg of the charge. The
+        // affiliate's share is sent as is, in a transfer of its own
+        debit = fee_schedule
+            .debit_for(
+                charge - affiliate_reward,
+                crank_reward,
+                ctx.accounts.revenue_split.as_deref(),
+                subscription.transfer_fee_mode,
+            )?
+            .checked_add(affiliate_reward)
+            .ok_or(SolBillError::Overflow)?;
+        subscription.check_max_token_amount(debit)?;
+        subscription.check_spending_caps(debit, clock.unix_timestamp)?;
 
-        // --- Transfer Logic ---
         let subscriber_key = subscription.subscriber;
         let original_plan_key = subscription.original_plan;
         let bump = subscription.bump;
@@ -97,84 +274,83 @@ pub fn handler(ctx: Context<CollectPayment>) -> Result<()> {
             &[bump],
         ]];
 
-        crate::instructions::utils::execute_token_transfer(
+        let payee_fee = crate::instructions::utils::execute_token_transfer(
             &ctx.accounts.token_program,
             &ctx.accounts.subscriber_token_account,
             &ctx.accounts.treasury,
             Some(&ctx.accounts.cranker_token_account),
             &ctx.accounts.accepted_mint,
             &ctx.accounts.subscription.to_account_info(),
-            subscription.amount,
-            subscription.crank_reward,
+            charge - affiliate_reward,
+            crank_reward,
             Some(signer_seeds),
+            ctx.accounts
+                .revenue_split
+                .as_deref()
+                .map(|split| (split, payees)),
+            subscription.transfer_fee_mode,
+            hook_accounts,
         )?;
+        let affiliate_fee = match ctx.accounts.affiliate_token_account.as_ref() {
+            Some(affiliate_token_account) => pay_affiliate(
+                &ctx.accounts.token_program,
+                &ctx.accounts.subscriber_token_account,
+                affiliate_token_account,
+                &ctx.accounts.accepted_mint,
+                &ctx.accounts.subscription.to_account_info(),
+                affiliate_reward,
+                Some(signer_seeds),
+                hook_accounts,
+            )?,
+            None => 0,
+        };
+        transfer_fee = payee_fee
+            .checked_add(affiliate_fee)
+            .ok_or(SolBillError::Overflow)?;
     }
 
-    // Now borrow mutably to update state
-    let subscription = &mut ctx.accounts.subscription;
-    let clock = Clock::get()?;
-
-    // --- Update subscription state ---
-    subscription.last_payment_timestamp = clock.unix_timestamp;
-
-    // Increment payments made
-    subscription.payments_made = subscription
-        .payments_made
-        .checked_add(1)
-        .ok_or(SolBillError::Overflow)?;
-
-    msg!(
-        "Payment collected. Total payments made: {}",
-        subscription.payments_made
-    );
-
-    // Check if we hit the limit
-    if subscription.max_billing_cycles > 0 {
-        msg!(
-            "Checking max cycles: {}/{}",
-            subscription.payments_made,
-            subscription.max_billing_cycles
-        );
-        if subscription.payments_made >= subscription.max_billing_cycles as u32 {
-            subscription.status = SubscriptionStatus::Completed;
-            // Prevent further billing
-            subscription.next_billing_timestamp = i64::MAX;
-            msg!("Max cycles reached. Status set to Completed.");
-        } else {
-            // Not yet completed, schedule next
-            subscription.next_billing_timestamp = clock
-                .unix_timestamp
-                .checked_add(subscription.interval)
-                .ok_or(SolBillError::Overflow)?;
-            subscription.status = SubscriptionStatus::Active;
-            msg!(
-                "Plan continues. Next billing: {}",
-                subscription.next_billing_timestamp
-            );
+    // Only a reward actually sent counts towards the referral's payouts
+    if affiliate_reward > 0 {
+        if let Some(affiliate) = ctx.accounts.affiliate.as_mut() {
+            affiliate.record_reward(affiliate_reward, charge)?;
         }
-    } else {
-        // Infinite
-        subscription.next_billing_timestamp = clock
-            .unix_timestamp
-            .checked_add(subscription.interval)
-            .ok_or(SolBillError::Overflow)?;
-        subscription.status = SubscriptionStatus::Active;
-        msg!(
-            "Infinite plan continues. Next billing: {}",
-            subscription.next_billing_timestamp
-        );
+        ctx.accounts.subscription.consume_referral();
     }
 
-    let treasury_amount = subscription
-        .amount
-        .checked_sub(subscription.crank_reward)
-        .unwrap_or(0);
+    // Now borrow mutably to update state
+    let subscription_key = ctx.accounts.subscription.key();
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.record_payment(debit, clock.unix_timestamp)?;
+    subscription.pending_charges = 0;
+    subscription.credit_balance -= credit_applied;
+
+    let treasury_amount = charge.saturating_sub(crank_reward + affiliate_reward);
 
     msg!(
-        "Collection success: Cranker Reward: {}, Treasury: {}, Next billing: {}",
-        subscription.crank_reward,
+        "Collection success: Cranker Reward: {} (+{} lamports), Treasury: {}, Next billing: {}",
+        crank_reward,
+        pool_reward,
         treasury_amount,
         subscription.next_billing_timestamp,
     );
+    emit!(PaymentCollected {
+        subscription: subscription_key,
+        subscriber: subscription.subscriber,
+        plan: subscription.plan,
+        amount: charge,
+        crank_reward,
+        pool_reward,
+        transfer_fee,
+        one_off_charges,
+        items,
+        late_fee,
+        affiliate_reward,
+        effective_price,
+        loyalty_discount_bps,
+        quantity: subscription.quantity,
+        credit_applied,
+        payments_made: subscription.payments_made,
+        next_billing_timestamp: subscription.next_billing_timestamp,
+    });
     Ok(())
 }
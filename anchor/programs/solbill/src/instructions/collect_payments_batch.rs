@@ -0,0 +1,545 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    close_account, CloseAccount, Mint, TokenAccount, TokenInterface,
+};
+
+use crate::errors::SolBillError;
+use crate::events::{PaymentCollected, PaymentSkipped};
+use crate::instructions::utils::{
+    can_collect, can_receive, execute_token_transfer, expected_revenue_split, pay_affiliate,
+    pay_from_reward_pool, read_quote_price, TransferFeeSchedule,
+};
+use crate::oracle::PriceData;
+use crate::state::{
+    Affiliate, PlanAccount, RevenueSplit, RewardPool, ServiceAccount, SubscriptionAccount,
+    TransferFeeMode,
+};
+
+/// Accounts per subscription in `remaining_accounts`:
+/// subscription, plan, subscriber token account, treasury.
+pub const BATCH_TUPLE_LEN: usize = 4;
+
+#[derive(Accounts)]
+pub struct CollectPaymentsBatch<'info> {
+    /// The public crank turner who triggers the payments and receives the rewards.
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The cranker's token account (destination for bounties/rewards).
+    #[account(
+        mut,
+        token::mint = accepted_mint,
+        constraint = cranker_token_account.owner == cranker.key() @ SolBillError::InvalidCrankerTokenAccount,
+    )]
+    pub cranker_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The mint every subscription in the batch pays in.
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Collects the batch's token crank rewards so the cranker is paid them in
+    /// one transfer at the end. Created and closed within the instruction.
+    #[account(
+        init,
+        payer = cranker,
+        seeds = [b"batch_rewards", cranker.key().as_ref()],
+        bump,
+        token::mint = accepted_mint,
+        token::authority = reward_vault,
+        token::token_program = token_program,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+
+    /// The service's lamport reward pool, required when one is configured.
+    #[account(
+        mut,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump = reward_pool.bump,
+        has_one = service,
+    )]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+    // remaining_accounts: per subscription, BATCH_TUPLE_LEN accounts and its
+    // extra accounts (see `handler`), then the transfer-hook program, its
+    // extra-account-metas account and extra accounts (hook mints only).
+}
+
+/// `extra_accounts` holds, per subscription, how many accounts follow its
+/// (subscription, plan, token account, treasury) tuple. In order: the price
+/// feed of a quote-priced subscription, the affiliate and payout account of a
+/// referral that still pays out, then the plan's revenue split and its payees.
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CollectPaymentsBatch<'info>>,
+    extra_accounts: Vec<u8>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let service = &ctx.accounts.service;
+
+    let tuples_len = extra_accounts
+        .iter()
+        .try_fold(0usize, |total, extra| {
+            total.checked_add(BATCH_TUPLE_LEN + *extra as usize)
+        })
+        .ok_or(SolBillError::Overflow)?;
+    require!(
+        !extra_accounts.is_empty() && ctx.remaining_accounts.len() >= tuples_len,
+        SolBillError::InvalidBatchAccounts
+    );
+    let (mut tuples, hook_accounts) = ctx.remaining_accounts.split_at(tuples_len);
+    require!(
+        service.reward_pool == ctx.accounts.reward_pool.as_ref().map(|p| p.key()),
+        SolBillError::RewardPoolMismatch
+    );
+
+    // A frozen cranker account would fail every rewarded collection
+    require!(
+        !ctx.accounts.cranker_token_account.is_frozen(),
+        SolBillError::TokenAccountFrozen
+    );
+
+    let cranker_key = ctx.accounts.cranker.key();
+    let is_merchant_collector = service.is_merchant_collector(&cranker_key);
+    let mint_key = ctx.accounts.accepted_mint.key();
+    let decimals = ctx.accounts.accepted_mint.decimals;
+
+    // How many collections the reward pool can fund; the rest fall back to
+    // token rewards. The pool pays once, in aggregate, after the batch.
+    let (pool_rate, mut pool_slots) = match &ctx.accounts.reward_pool {
+        Some(pool) if pool.lamports_per_crank > 0 => {
+            let available = RewardPool::available_lamports(&pool.to_account_info())?;
+            (pool.lamports_per_crank, available / pool.lamports_per_crank)
+        }
+        _ => (0, 0),
+    };
+
+    let fee_schedule = TransferFeeSchedule::load(&ctx.accounts.accepted_mint.to_account_info())?;
+    // Routing a reward through the vault would charge a mint's transfer fee
+    // twice, so such mints pay each reward to the cranker directly instead
+    let reward_account = if fee_schedule.has_fee() {
+        &ctx.accounts.cranker_token_account
+    } else {
+        &ctx.accounts.reward_vault
+    };
+
+    let mut collected: u64 = 0;
+    let mut pool_funded: u64 = 0;
+    let mut total_token_reward: u64 = 0;
+
+    for extra in extra_accounts.iter() {
+        let mut tuple = take_accounts(&mut tuples, BATCH_TUPLE_LEN + *extra as usize)?;
+        let [subscription_info, plan_info, token_info, treasury_info] =
+            take_accounts(&mut tuple, BATCH_TUPLE_LEN)?
+        else {
+            return err!(SolBillError::InvalidBatchAccounts);
+        };
+
+        // --- Validate the tuple as CollectPayment's constraints would ---
+        // A subscription closed (cancelled or expired) since the batch was
+        // assembled no longer deserializes; it is skipped, not fatal
+        let mut subscription = match Account::<SubscriptionAccount>::try_from(subscription_info) {
+            Ok(subscription) => Box::new(subscription),
+            Err(err) => {
+                emit_skipped(subscription_info.key(), err);
+                continue;
+            }
+        };
+        let expected_subscription = Pubkey::create_program_address(
+            &[
+                b"subscription",
+                subscription.subscriber.as_ref(),
+                subscription.original_plan.as_ref(),
+                &[subscription.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| SolBillError::InvalidBatchAccounts)?;
+        require_keys_eq!(
+            expected_subscription,
+            subscription_info.key(),
+            SolBillError::InvalidBatchAccounts
+        );
+        require_keys_eq!(
+            subscription.service,
+            service.key(),
+            SolBillError::InvalidBatchAccounts
+        );
+
+        let plan = Box::new(Account::<PlanAccount>::try_from(plan_info)?);
+        require_keys_eq!(
+            plan.key(),
+            subscription.plan,
+            SolBillError::InvalidBatchAccounts
+        );
+
+        // Native SOL subscriptions have an escrow, not a token account, and a
+        // batch settles in a single mint
+        let unsupported = if subscription.native_sol {
+            Some(error!(SolBillError::PaymentMethodMismatch))
+        } else if subscription.mint != mint_key {
+            Some(error!(SolBillError::PlanMintMismatch))
+        } else {
+            None
+        };
+        if let Some(err) = unsupported {
+            emit_skipped(subscription_info.key(), err);
+            continue;
+        }
+
+        // The extra accounts must be exactly the ones the subscription needs
+        let price_feed = match subscription.quote_pricing {
+            Some(_) => Some(&take_accounts(&mut tuple, 1)?[0]),
+            None => None,
+        };
+        let referral = match &subscription.referral {
+            Some(referral) => {
+                let [affiliate_info, payout_info] = take_accounts(&mut tuple, 2)? else {
+                    return err!(SolBillError::InvalidBatchAccounts);
+                };
+                require!(
+                    affiliate_info.key() == referral.affiliate
+                        && payout_info.key() == referral.payout_token_account,
+                    SolBillError::AffiliateMismatch
+                );
+                let affiliate = Box::new(Account::<Affiliate>::try_from(affiliate_info)?);
+                Some((*referral, affiliate, payout_info))
+            }
+            None => None,
+        };
+        let revenue_split = match expected_revenue_split(service, &plan, &subscription.mint) {
+            Some(split_key) => {
+                let split_info = &take_accounts(&mut tuple, 1)?[0];
+                require_keys_eq!(
+                    split_info.key(),
+                    split_key,
+                    SolBillError::RevenueSplitMismatch
+                );
+                let split = Box::new(Account::<RevenueSplit>::try_from(split_info)?);
+                let payees = take_accounts(&mut tuple, split.recipients.len())?;
+                Some((split, payees))
+            }
+            None => None,
+        };
+        require!(tuple.is_empty(), SolBillError::InvalidBatchAccounts);
+
+        // The right accounts are required; one that has since been closed
+        // only skips the subscription
+        require_keys_eq!(
+            token_info.key(),
+            subscription.subscriber_token_account,
+            SolBillError::InvalidBatchAccounts
+        );
+        require_keys_eq!(
+            treasury_info.key(),
+            subscription.treasury,
+            SolBillError::InvalidBatchAccounts
+        );
+        let (subscriber_token_account, treasury) = match (
+            InterfaceAccount::<TokenAccount>::try_from(token_info),
+            InterfaceAccount::<TokenAccount>::try_from(treasury_info),
+        ) {
+            (Ok(subscriber_token_account), Ok(treasury)) => (subscriber_token_account, treasury),
+            (Err(err), _) | (_, Err(err)) => {
+                emit_skipped(subscription_info.key(), err);
+                continue;
+            }
+        };
+
+        // Quote-priced amounts are converted at the current price. Add-ons,
+        // merchant one-off charges and any late fee ride along with the
+        // recurring charge. A stale price or a charge that overflows is
+        // skipped, not fatal
+        let pricing = (|| -> Result<(Option<PriceData>, u64, u64, u64, u64, u64)> {
+            let price = read_quote_price(
+                subscription.quote_pricing.as_ref(),
+                price_feed,
+                clock.unix_timestamp,
+            )?;
+            let amount = subscription.effective_amount()?;
+            let recurring = subscription.to_token_amount(amount, price.as_ref(), decimals)?;
+            // Credit from removed seats comes off the recurring charge
+            let credit_applied = subscription.credit_balance.min(recurring);
+            let recurring = recurring - credit_applied;
+            let late_fee = subscription.to_token_amount(
+                subscription.late_fee_for(amount)?,
+                price.as_ref(),
+                decimals,
+            )?;
+            let charge = recurring
+                .checked_add(subscription.items_amount()?)
+                .and_then(|total| total.checked_add(subscription.pending_charges))
+                .and_then(|total| total.checked_add(late_fee))
+                .ok_or(SolBillError::Overflow)?;
+            Ok((price, amount, late_fee, credit_applied, recurring, charge))
+        })();
+        let (price, amount, late_fee, credit_applied, recurring, charge) = match pricing {
+            Ok(pricing) => pricing,
+            Err(err) => {
+                emit_skipped(subscription_info.key(), err);
+                continue;
+            }
+        };
+        let one_off_charges = subscription.pending_charges;
+        let loyalty_discount_bps = subscription.loyalty_discount_bps();
+
+        // --- Skip anything not collectable right now instead of aborting ---
+        let exclusive =
+            plan.in_exclusive_window(subscription.next_billing_timestamp, clock.unix_timestamp);
+        let funded_by_pool = !exclusive && pool_slots > 0;
+        let split = revenue_split
+            .as_ref()
+            .map(|(split, payees)| (&***split, *payees));
+        // A reward that overflows is skipped, not fatal
+        let rewards = (|| -> Result<(u64, u64, u64)> {
+            let reward = if exclusive || funded_by_pool {
+                0
+            } else {
+                let reward = subscription.crank_reward_for(amount, clock.unix_timestamp)?;
+                subscription
+                    .to_token_amount(reward, price.as_ref(), decimals)?
+                    .min(recurring)
+            };
+            // The referring affiliate's share comes out of the merchant's
+            // portion; a payout account that can't receive leaves it for a
+            // later collection
+            let affiliate_reward = match &referral {
+                Some((referral, _, payout_info))
+                    if can_receive(payout_info, &mint_key, ctx.accounts.token_program.key) =>
+                {
+                    referral
+                        .reward_for(recurring)?
+                        .min(recurring.saturating_sub(reward))
+                }
+                _ => 0,
+            };
+            // The cranker's leg is grossed up for the mint's transfer fee, so a
+            // heavily discounted charge may not carry the whole reward
+            let crank_reward = fee_schedule.fit_reward(
+                reward,
+                charge - affiliate_reward,
+                subscription.transfer_fee_mode,
+            )?;
+            // Caps are held to what leaves the wallet, fee included. The
+            // affiliate's share is sent as is, in a transfer of its own
+            let debit = fee_schedule
+                .debit_for(
+                    charge - affiliate_reward,
+                    crank_reward,
+                    split.map(|(split, _)| split),
+                    subscription.transfer_fee_mode,
+                )?
+                .checked_add(affiliate_reward)
+                .ok_or(SolBillError::Overflow)?;
+            Ok((crank_reward, affiliate_reward, debit))
+        })();
+        let (crank_reward, affiliate_reward, debit) = match rewards {
+            Ok(rewards) => rewards,
+            Err(err) => {
+                emit_skipped(subscription_info.key(), err);
+                continue;
+            }
+        };
+        // Frozen or closed payees would fail the transfer
+        let payable = match split {
+            Some((_, payees)) => payees
+                .iter()
+                .all(|payee| can_receive(payee, &mint_key, ctx.accounts.token_program.key)),
+            None => !treasury.is_frozen(),
+        };
+        let skip_reason = if let Err(err) = subscription.check_collectable(clock.unix_timestamp) {
+            Some(err)
+        } else if !payable {
+            Some(error!(SolBillError::TokenAccountFrozen))
+        } else if exclusive && !is_merchant_collector {
+            Some(error!(SolBillError::ExclusiveCollectionWindow))
+        } else if let Err(err) = subscription.check_max_token_amount(debit) {
+            Some(err)
+        } else if let Err(err) = subscription.check_spending_caps(debit, clock.unix_timestamp) {
+            Some(err)
+        } else if !can_collect(&subscriber_token_account, &subscription_info.key(), debit) {
+            Some(error!(SolBillError::InsufficientFunds))
+        } else {
+            None
+        };
+
+        if let Some(err) = skip_reason {
+            emit_skipped(subscription_info.key(), err);
+            continue;
+        }
+
+        // --- Transfer ---
+        let subscriber_key = subscription.subscriber;
+        let original_plan_key = subscription.original_plan;
+        let bump = subscription.bump;
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"subscription",
+            subscriber_key.as_ref(),
+            original_plan_key.as_ref(),
+            &[bump],
+        ]];
+
+        // The merchant's share goes straight to the treasury (or the payees)
+        let mut transfer_fee = execute_token_transfer(
+            &ctx.accounts.token_program,
+            &subscriber_token_account,
+            &treasury,
+            Some(reward_account),
+            &ctx.accounts.accepted_mint,
+            subscription_info,
+            charge - affiliate_reward,
+            crank_reward,
+            Some(signer_seeds),
+            split,
+            subscription.transfer_fee_mode,
+            hook_accounts,
+        )?;
+
+        // --- Update state and persist it ---
+        if let Some((_, mut affiliate, payout_info)) = referral {
+            if affiliate_reward > 0 {
+                let affiliate_fee = pay_affiliate(
+                    &ctx.accounts.token_program,
+                    &subscriber_token_account,
+                    payout_info,
+                    &ctx.accounts.accepted_mint,
+                    subscription_info,
+                    affiliate_reward,
+                    Some(signer_seeds),
+                    hook_accounts,
+                )?;
+                transfer_fee = transfer_fee
+                    .checked_add(affiliate_fee)
+                    .ok_or(SolBillError::Overflow)?;
+                affiliate.record_reward(affiliate_reward, charge)?;
+                affiliate.exit(&crate::ID)?;
+                subscription.consume_referral();
+            } else {
+                msg!("Affiliate payout account can't receive, reward deferred");
+            }
+        }
+        subscription.record_payment(debit, clock.unix_timestamp)?;
+        subscription.pending_charges = 0;
+        subscription.credit_balance -= credit_applied;
+        subscription.exit(&crate::ID)?;
+
+        let pool_reward = if funded_by_pool {
+            pool_slots -= 1;
+            pool_funded += 1;
+            pool_rate
+        } else {
+            0
+        };
+        collected += 1;
+        total_token_reward = total_token_reward
+            .checked_add(crank_reward)
+            .ok_or(SolBillError::Overflow)?;
+
+        emit!(PaymentCollected {
+            subscription: subscription_info.key(),
+            subscriber: subscription.subscriber,
+            plan: subscription.plan,
+            amount: charge,
+            crank_reward,
+            pool_reward,
+            transfer_fee,
+            one_off_charges,
+            items: subscription.items.clone(),
+            late_fee,
+            affiliate_reward,
+            effective_price: amount,
+            loyalty_discount_bps,
+            quantity: subscription.quantity,
+            credit_applied,
+            payments_made: subscription.payments_made,
+            next_billing_timestamp: subscription.next_billing_timestamp,
+        });
+    }
+
+    // One combined token reward, then the vault's rent goes back to the cranker
+    let vault_seeds: &[&[&[u8]]] = &[&[
+        b"batch_rewards",
+        cranker_key.as_ref(),
+        &[ctx.bumps.reward_vault],
+    ]];
+    ctx.accounts.reward_vault.reload()?;
+    let vault_balance = ctx.accounts.reward_vault.amount;
+    if vault_balance > 0 {
+        execute_token_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.reward_vault,
+            &ctx.accounts.cranker_token_account,
+            None,
+            &ctx.accounts.accepted_mint,
+            &ctx.accounts.reward_vault.to_account_info(),
+            vault_balance,
+            0,
+            Some(vault_seeds),
+            None,
+            TransferFeeMode::MerchantAbsorbs,
+            hook_accounts,
+        )?;
+    }
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.reward_vault.to_account_info(),
+            destination: ctx.accounts.cranker.to_account_info(),
+            authority: ctx.accounts.reward_vault.to_account_info(),
+        },
+        vault_seeds,
+    ))?;
+
+    // One aggregated lamport reward for every pool-funded collection
+    let total_pool_reward = pool_funded
+        .checked_mul(pool_rate)
+        .ok_or(SolBillError::Overflow)?;
+    if let Some(pool) = ctx.accounts.reward_pool.as_mut() {
+        if total_pool_reward > 0 {
+            let paid = pay_from_reward_pool(
+                pool,
+                &ctx.accounts.cranker.to_account_info(),
+                total_pool_reward,
+            )?;
+            require!(paid, SolBillError::InsufficientPoolBalance);
+        }
+    }
+
+    msg!(
+        "Batch collected {}/{} subscriptions — Cranker Reward: {} (+{} lamports)",
+        collected,
+        extra_accounts.len(),
+        total_token_reward,
+        total_pool_reward,
+    );
+    Ok(())
+}
+
+/// Split the next `count` accounts off the front of `accounts`.
+fn take_accounts<'a, 'info>(
+    accounts: &mut &'a [AccountInfo<'info>],
+    count: usize,
+) -> Result<&'a [AccountInfo<'info>]> {
+    require!(accounts.len() >= count, SolBillError::InvalidBatchAccounts);
+    let (taken, rest) = accounts.split_at(count);
+    *accounts = rest;
+    Ok(taken)
+}
+
+/// Emit a `PaymentSkipped` for a subscription left untouched because of `err`.
+fn emit_skipped(subscription: Pubkey, err: Error) {
+    let error_code = match err {
+        Error::AnchorError(anchor_error) => anchor_error.error_code_number,
+        Error::ProgramError(_) => u32::MAX,
+    };
+    emit!(PaymentSkipped {
+        subscription,
+        error_code,
+    });
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{Coupon, CouponDuration, Discount, ServiceAccount};
+
+#[derive(Accounts)]
+#[instruction(code: [u8; 32])]
+pub struct CreateCoupon<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Coupon::INIT_SPACE,
+        seeds = [b"coupon", service.key().as_ref(), code.as_ref()],
+        bump,
+    )]
+    pub coupon: Account<'info, Coupon>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<CreateCoupon>,
+    code: [u8; 32],
+    discount: Discount,
+    duration: CouponDuration,
+    max_redemptions: u32,
+    expires_at: i64,
+    eligible_plans: Vec<Pubkey>,
+) -> Result<()> {
+    let coupon = &mut ctx.accounts.coupon;
+    coupon.service = ctx.accounts.service.key();
+    coupon.code = code;
+    coupon.discount = discount;
+    coupon.duration = duration;
+    coupon.max_redemptions = max_redemptions;
+    coupon.redemptions = 0;
+    coupon.expires_at = expires_at;
+    coupon.eligible_plans = eligible_plans;
+    coupon.bump = ctx.bumps.coupon;
+    coupon.validate()?;
+
+    msg!(
+        "Coupon created: {} (max redemptions: {}, expires: {})",
+        coupon.key(),
+        max_redemptions,
+        expires_at,
+    );
+    Ok(())
+}
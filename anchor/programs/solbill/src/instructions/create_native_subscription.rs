@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct CreateNativeSubscription<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+        constraint = plan.is_active @ SolBillError::PlanNotActive,
+        constraint = plan.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + SubscriptionAccount::INIT_SPACE,
+        seeds = [b"subscription", subscriber.key().as_ref(), plan.key().as_ref()],
+        bump,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// Lamport escrow that later payments are debited from.
+    #[account(
+        mut,
+        seeds = [b"escrow", subscription.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    /// The merchant's wallet receiving lamport payments.
+    #[account(
+        mut,
+        constraint = service.sol_treasury == Some(sol_treasury.key()) @ SolBillError::NativeSolNotEnabled,
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<CreateNativeSubscription>, prefund: u64) -> Result<()> {
+    let plan = &ctx.accounts.plan;
+    let clock = Clock::get()?;
+
+    // The escrow is a plain system account, so it must hold at least its
+    // rent-exempt minimum once funded.
+    require!(
+        prefund == 0 || prefund >= Rent::get()?.minimum_balance(0),
+        SolBillError::InsufficientFunds
+    );
+
+    let first_payment = {
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.subscriber = ctx.accounts.subscriber.key();
+        subscription.service = ctx.accounts.service.key();
+        subscription.original_plan = plan.key();
+        subscription.plan = plan.key();
+        subscription.subscriber_token_account = ctx.accounts.escrow.key();
+        subscription.generation = ctx.accounts.service.next_generation()?;
+        subscription.lock_in_terms(plan);
+        subscription.bump = ctx.bumps.subscription;
+        subscription.start_billing(clock.unix_timestamp)?;
+        // Any activation fee is paid together with the first period
+        let first_payment = subscription
+            .recurring_amount()?
+            .checked_add(plan.setup_fee)
+            .ok_or(SolBillError::Overflow)?;
+        subscription.record_spend(first_payment, clock.unix_timestamp)?;
+        subscription.advance_phase()?;
+        first_payment
+    };
+
+    // Free tiers pay nothing up front
+    if first_payment > 0 {
+        // First payment goes straight from the subscriber's wallet
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.subscriber.to_account_info(),
+                    to: ctx.accounts.sol_treasury.to_account_info(),
+                },
+            ),
+            first_payment,
+        )?;
+    }
+
+    if prefund > 0 {
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.subscriber.to_account_info(),
+                    to: ctx.accounts.escrow.to_account_info(),
+                },
+            ),
+            prefund,
+        )?;
+    }
+
+    let service = &mut ctx.accounts.service;
+    service.subscriber_count = service
+        .subscriber_count
+        .checked_add(1)
+        .ok_or(SolBillError::Overflow)?;
+
+    msg!(
+        "Native subscription created & paid: {} -> plan {} (escrow prefund: {} lamports)",
+        ctx.accounts.subscriber.key(),
+        plan.plan_index,
+        prefund,
+    );
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 
 use crate::errors::SolBillError;
-use crate::state::{PlanAccount, ServiceAccount};
+use crate::state::{PercentageReward, PlanAccount, ServiceAccount, TransferFeeMode};
 
 #[derive(Accounts)]
 pub struct CreatePlan<'info> {
@@ -28,6 +28,7 @@ pub struct CreatePlan<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handler(
     ctx: Context<CreatePlan>,
     name: String,
@@ -36,14 +37,22 @@ pub fn handler(
     interval: i64,
     grace_period: i64,
     max_billing_cycles: u64,
+    percentage_reward: Option<PercentageReward>,
+    native_sol: bool,
 ) -> Result<()> {
     require!(
         !name.is_empty() && name.len() <= 32,
         SolBillError::InvalidPlanName
     );
-    require!(amount > 0, SolBillError::InvalidAmount);
+    // A zero amount makes a free tier, which never pays a crank reward
     require!(interval > 0, SolBillError::InvalidInterval);
-    require!(crank_reward < amount, SolBillError::InvalidCrankReward);
+    require!(
+        crank_reward == 0 || crank_reward < amount,
+        SolBillError::InvalidCrankReward
+    );
+    if let Some(policy) = &percentage_reward {
+        policy.validate(amount)?;
+    }
 
     let plan = &mut ctx.accounts.plan;
     let service = &mut ctx.accounts.service;
@@ -63,6 +72,22 @@ pub fn handler(
     plan.grace_period = grace_period;
     plan.plan_index = service.plan_count;
     plan.max_billing_cycles = max_billing_cycles;
+    plan.revenue_split = None;
+    plan.percentage_reward = percentage_reward;
+    plan.reward_escalation = None;
+    plan.exclusive_window = 0;
+    plan.native_sol = native_sol;
+    plan.transfer_fee_mode = TransferFeeMode::MerchantAbsorbs;
+    plan.accepted_mint = None;
+    plan.treasury = None;
+    plan.quote_pricing = None;
+    plan.setup_fee = 0;
+    plan.late_fee = None;
+    plan.loyalty_tiers = Vec::new();
+    plan.price_phases = Vec::new();
+    plan.pay_what_you_want = None;
+    plan.min_quantity = 1;
+    plan.max_quantity = 1;
     plan.bump = ctx.bumps.plan;
 
     // Increment the service's plan counter
@@ -0,0 +1,124 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::errors::SolBillError;
+use crate::state::{
+    PlanAccount, RevenueSplit, ServiceAccount, SplitRecipient, BPS_DENOMINATOR,
+    MAX_SPLIT_RECIPIENTS,
+};
+
+#[derive(Accounts)]
+pub struct CreateRevenueSplit<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// Plan to attach the split to. When omitted, the split applies service-wide.
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Option<Account<'info, PlanAccount>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RevenueSplit::INIT_SPACE,
+        seeds = [
+            b"split",
+            plan.as_ref().map(|p| p.key()).unwrap_or(service.key()).as_ref(),
+        ],
+        bump,
+    )]
+    pub revenue_split: Account<'info, RevenueSplit>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: one token account per recipient, in order.
+}
+
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateRevenueSplit<'info>>,
+    recipients: Vec<SplitRecipient>,
+    primary_index: u8,
+) -> Result<()> {
+    require!(
+        !recipients.is_empty() && recipients.len() <= MAX_SPLIT_RECIPIENTS,
+        SolBillError::InvalidRevenueSplit
+    );
+    require!(
+        (primary_index as usize) < recipients.len(),
+        SolBillError::InvalidRevenueSplit
+    );
+    require!(
+        ctx.remaining_accounts.len() == recipients.len(),
+        SolBillError::InvalidSplitRecipient
+    );
+
+    let accepted_mint = match &ctx.accounts.plan {
+        Some(plan) => plan.mint_for(&ctx.accounts.service),
+        None => ctx.accounts.service.accepted_mint,
+    };
+
+    let mut total_bps: u64 = 0;
+    for (recipient, account_info) in recipients.iter().zip(ctx.remaining_accounts.iter()) {
+        require!(recipient.share_bps > 0, SolBillError::InvalidRevenueSplit);
+        total_bps = total_bps
+            .checked_add(recipient.share_bps as u64)
+            .ok_or(SolBillError::Overflow)?;
+
+        // Each payee must be a live token account of the accepted mint, otherwise
+        // every future collection would fail on transfer.
+        require_keys_eq!(
+            account_info.key(),
+            recipient.token_account,
+            SolBillError::InvalidSplitRecipient
+        );
+        let token_account = InterfaceAccount::<TokenAccount>::try_from(account_info)
+            .map_err(|_| SolBillError::InvalidSplitRecipient)?;
+        require_keys_eq!(
+            token_account.mint,
+            accepted_mint,
+            SolBillError::InvalidSplitRecipient
+        );
+    }
+    require!(
+        total_bps == BPS_DENOMINATOR,
+        SolBillError::InvalidRevenueSplit
+    );
+
+    let split_key = ctx.accounts.revenue_split.key();
+    let target = match ctx.accounts.plan.as_mut() {
+        Some(plan) => {
+            plan.revenue_split = Some(split_key);
+            plan.key()
+        }
+        None => {
+            ctx.accounts.service.revenue_split = Some(split_key);
+            ctx.accounts.service.key()
+        }
+    };
+
+    let revenue_split = &mut ctx.accounts.revenue_split;
+    revenue_split.service = ctx.accounts.service.key();
+    revenue_split.target = target;
+    revenue_split.recipients = recipients;
+    revenue_split.primary_index = primary_index;
+    revenue_split.bump = ctx.bumps.revenue_split;
+
+    msg!(
+        "Revenue split created for {} ({} recipients, primary {})",
+        target,
+        revenue_split.recipients.len(),
+        primary_index,
+    );
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{RewardPool, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct CreateRewardPool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + RewardPool::INIT_SPACE,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(
+    ctx: Context<CreateRewardPool>,
+    lamports_per_crank: u64,
+    maintenance_reward: u64,
+) -> Result<()> {
+    require!(lamports_per_crank > 0, SolBillError::InvalidAmount);
+
+    let reward_pool = &mut ctx.accounts.reward_pool;
+    reward_pool.service = ctx.accounts.service.key();
+    reward_pool.lamports_per_crank = lamports_per_crank;
+    reward_pool.maintenance_reward = maintenance_reward;
+    reward_pool.total_paid = 0;
+    reward_pool.bump = ctx.bumps.reward_pool;
+
+    ctx.accounts.service.reward_pool = Some(reward_pool.key());
+
+    msg!(
+        "Reward pool created for service {} ({} lamports per crank, {} per maintenance crank)",
+        reward_pool.service,
+        lamports_per_crank,
+        maintenance_reward,
+    );
+    Ok(())
+}
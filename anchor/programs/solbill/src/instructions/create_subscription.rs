@@ -1,10 +1,19 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{approve, Approve, Mint, TokenAccount, TokenInterface};
+use anchor_lang::solana_program::sysvar::instructions as instructions_sysvar;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 use crate::errors::SolBillError;
-use crate::state::{PlanAccount, ServiceAccount, SubscriptionAccount, SubscriptionStatus};
+use crate::instructions::utils::{
+    approve_delegation, read_quote_price, split_remaining_accounts, validate_revenue_split,
+    verify_ed25519_signature, TransferFeeSchedule,
+};
+use crate::state::{
+    Affiliate, Coupon, OfferRedemption, PlanAccount, PrivateOffer, RevenueSplit, ServiceAccount,
+    SubscriptionAccount,
+};
 
 #[derive(Accounts)]
+#[instruction(max_token_amount: u64, offer: Option<PrivateOffer>)]
 pub struct CreateSubscription<'info> {
     #[account(mut)]
     pub subscriber: Signer<'info>,
@@ -21,6 +30,7 @@ pub struct CreateSubscription<'info> {
         bump = plan.bump,
         has_one = service,
         constraint = plan.is_active @ SolBillError::PlanNotActive,
+        constraint = !plan.native_sol @ SolBillError::PaymentMethodMismatch,
     )]
     pub plan: Account<'info, PlanAccount>,
 
@@ -44,24 +54,155 @@ pub struct CreateSubscription<'info> {
 
     /// The SPL mint accepted by the service.
     #[account(
-        address = service.accepted_mint,
+        address = plan.mint_for(&service),
     )]
     pub accepted_mint: InterfaceAccount<'info, Mint>,
 
     /// The merchant's treasury token account (destination for first payment).
     #[account(
         mut,
-        address = service.treasury,
+        address = plan.treasury_for(&service),
     )]
     pub treasury: InterfaceAccount<'info, TokenAccount>,
 
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    /// The revenue split configured on the plan or service, if any.
+    /// Its recipients' token accounts are passed as remaining accounts.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+
+    /// CHECK: The plan's price account (quote-priced plans only); matched
+    /// against `plan.quote_pricing` and parsed by the price reader.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    /// Coupon to redeem onto the new subscription, if any.
+    #[account(
+        mut,
+        has_one = service,
+    )]
+    pub coupon: Option<Account<'info, Coupon>>,
+
+    /// CHECK: The instructions sysvar, required with a private offer to find
+    /// the ed25519 signature check preceding this instruction.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// Affiliate that referred the subscriber, if any.
+    #[account(
+        mut,
+        has_one = service,
+        constraint = affiliate.wallet != subscriber.key() @ SolBillError::SelfReferral,
+        constraint = accepted_mint.key() == service.accepted_mint @ SolBillError::AffiliateMintMismatch,
+    )]
+    pub affiliate: Option<Account<'info, Affiliate>>,
+
+    /// Marker recording the private offer as used, required with an offer.
+    #[account(
+        init,
+        payer = subscriber,
+        space = 8 + OfferRedemption::INIT_SPACE,
+        seeds = [
+            b"offer",
+            service.key().as_ref(),
+            offer.map_or(0, |offer| offer.nonce).to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub offer_redemption: Option<Account<'info, OfferRedemption>>,
+    // remaining_accounts: split payees, then the transfer-hook program, its
+    // extra-account-metas account and extra accounts (hook mints only).
 }
 
-pub fn handler(ctx: Context<CreateSubscription>) -> Result<()> {
+pub fn handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateSubscription<'info>>,
+    max_token_amount: u64,
+    offer: Option<PrivateOffer>,
+    chosen_amount: Option<u64>,
+) -> Result<()> {
     let plan = &ctx.accounts.plan;
     let clock = Clock::get()?;
+    let fee_schedule = TransferFeeSchedule::load(&ctx.accounts.accepted_mint.to_account_info())?;
+    // Crank rewards are sent grossed up for the mint's transfer fee, so a
+    // price must cover them net of it. Quote-priced plans are priced in
+    // quote units, which no fee is taken in
+    let net_price = |amount: u64| match plan.quote_pricing {
+        Some(_) => Ok(amount),
+        None => fee_schedule.net_of(amount),
+    };
+    if fee_schedule.has_fee() && !plan.is_free() {
+        plan.validate_rewards(net_price(plan.lowest_amount())?)?;
+    }
+
+    // A private offer must be signed by the service authority in an ed25519
+    // precompile instruction immediately before this one
+    if let Some(offer) = &offer {
+        offer.check(
+            &ctx.accounts.service.key(),
+            &plan.key(),
+            &ctx.accounts.subscriber.key(),
+            clock.unix_timestamp,
+        )?;
+        // The negotiated price has to cover the plan's crank rewards, as a
+        // phase or pay-what-you-want price does
+        plan.validate_rewards(net_price(offer.amount)?)?;
+        let sysvar = ctx
+            .accounts
+            .instructions_sysvar
+            .as_ref()
+            .ok_or(SolBillError::InvalidOfferSignature)?;
+        verify_ed25519_signature(sysvar, &ctx.accounts.service.authority, &offer.message()?)?;
+
+        // Each offer is accepted once; its marker outlives the subscription
+        let redemption = ctx
+            .accounts
+            .offer_redemption
+            .as_mut()
+            .ok_or(SolBillError::OfferMismatch)?;
+        redemption.service = ctx.accounts.service.key();
+        redemption.subscriber = ctx.accounts.subscriber.key();
+        redemption.nonce = offer.nonce;
+        redemption.redeemed_at = clock.unix_timestamp;
+        redemption.bump = ctx.bumps.offer_redemption.unwrap_or_default();
+    }
+    require!(
+        offer.is_some() || ctx.accounts.offer_redemption.is_none(),
+        SolBillError::OfferMismatch
+    );
+
+    // Pay-what-you-want plans take the subscriber's amount within their range;
+    // an offer's negotiated price would be open to change afterwards
+    require!(
+        offer.is_none() || plan.pay_what_you_want.is_none(),
+        SolBillError::PricingModeConflict
+    );
+    if let Some(amount) = chosen_amount {
+        plan.pay_what_you_want
+            .ok_or(SolBillError::AmountOutOfRange)?
+            .check(amount)?;
+    }
+
+    // Quote-priced plans convert at today's price, bounded by the subscriber's cap
+    require!(
+        plan.quote_pricing.is_none() || max_token_amount > 0,
+        SolBillError::MaxTokenAmountExceeded
+    );
+    let price = read_quote_price(
+        plan.quote_pricing.as_ref(),
+        ctx.accounts.price_feed.as_deref(),
+        clock.unix_timestamp,
+    )?;
+
+    validate_revenue_split(
+        &ctx.accounts.service,
+        plan,
+        &ctx.accounts.accepted_mint.key(),
+        ctx.accounts.revenue_split.as_ref(),
+    )?;
+    let (payees, hook_accounts) = split_remaining_accounts(
+        ctx.remaining_accounts,
+        ctx.accounts.revenue_split.as_deref(),
+    )?;
 
     // Initialize the subscription in a scoped block to drop the mutable borrow
     {
@@ -71,79 +212,94 @@ pub fn handler(ctx: Context<CreateSubscription>) -> Result<()> {
         subscription.original_plan = plan.key();
         subscription.plan = plan.key();
         subscription.subscriber_token_account = ctx.accounts.subscriber_token_account.key();
-        subscription.amount = plan.amount;
-        subscription.crank_reward = plan.crank_reward;
-        subscription.interval = plan.interval;
-        subscription.max_billing_cycles = plan.max_billing_cycles;
-        subscription.payments_made = 1;
+        subscription.mint = ctx.accounts.accepted_mint.key();
+        subscription.treasury = ctx.accounts.treasury.key();
+        subscription.generation = ctx.accounts.service.next_generation()?;
+        subscription.lock_in_terms(plan);
+        if let Some(offer) = &offer {
+            subscription.amount = offer.amount;
+            subscription.interval = offer.interval;
+            subscription.price_phases.clear();
+            subscription.from_offer = true;
+        }
+        if let Some(amount) = chosen_amount {
+            subscription.amount = amount;
+        }
+        subscription.max_token_amount = max_token_amount;
         subscription.bump = ctx.bumps.subscription;
-
-        msg!(
-            "Creating subscription. Plan max_cycles: {}",
-            plan.max_billing_cycles
-        );
-
-        // Logic for One-Time Payments vs Recurring
-        if plan.max_billing_cycles > 0 {
-            if plan.max_billing_cycles == 1 {
-                // If it's a one-time payment, we set the status to Completed immediately
-                // because the user pays upfront in this transaction (see execute_token_transfer below).
-                subscription.status = SubscriptionStatus::Completed;
-                // Prevent future billing
-                subscription.next_billing_timestamp = i64::MAX;
-                msg!("One-time payment plan. Status set to Completed.");
-            } else {
-                // It's a finite recurring plan (e.g. 3 months)
-                subscription.status = SubscriptionStatus::Active;
-                subscription.next_billing_timestamp = clock
-                    .unix_timestamp
-                    .checked_add(plan.interval)
-                    .ok_or(SolBillError::Overflow)?;
-                msg!(
-                    "Finite plan ({} cycles). Status Active. Next bill: {}",
-                    plan.max_billing_cycles,
-                    subscription.next_billing_timestamp
-                );
-            }
-        } else {
-            // Infinite recurring
-            subscription.status = SubscriptionStatus::Active;
-            subscription.next_billing_timestamp = clock
-                .unix_timestamp
-                .checked_add(plan.interval)
-                .ok_or(SolBillError::Overflow)?;
-            msg!(
-                "Infinite plan. Status Active. Next bill: {}",
-                subscription.next_billing_timestamp
-            );
+        subscription.start_billing(clock.unix_timestamp)?;
+        if let Some(coupon) = ctx.accounts.coupon.as_mut() {
+            subscription.redeem_coupon(coupon.key(), coupon, &plan.key(), clock.unix_timestamp)?;
+        }
+        if let Some(affiliate) = ctx.accounts.affiliate.as_mut() {
+            let affiliate_key = affiliate.key();
+            subscription.referral = Some(affiliate.refer(affiliate_key)?);
         }
     }
 
-    // Approve the subscription PDA as delegate on subscriber's token account
-    approve(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Approve {
-                to: ctx.accounts.subscriber_token_account.to_account_info(),
-                delegate: ctx.accounts.subscription.to_account_info(),
-                authority: ctx.accounts.subscriber.to_account_info(),
-            },
-        ),
-        plan.amount,
+    let subscription = &ctx.accounts.subscription;
+    let decimals = ctx.accounts.accepted_mint.decimals;
+    let charge = subscription.to_token_amount(
+        subscription.discounted(subscription.recurring_amount()?)?,
+        price.as_ref(),
+        decimals,
     )?;
+    let revenue_split = ctx.accounts.revenue_split.as_deref();
+    subscription.check_max_token_amount(fee_schedule.debit_for(
+        charge,
+        0,
+        revenue_split,
+        plan.transfer_fee_mode,
+    )?)?;
+    // Any activation fee is paid together with the first period
+    let setup_fee = subscription.to_token_amount(plan.setup_fee, price.as_ref(), decimals)?;
+    let first_payment = charge
+        .checked_add(setup_fee)
+        .ok_or(SolBillError::Overflow)?;
+    let debit = fee_schedule.debit_for(first_payment, 0, revenue_split, plan.transfer_fee_mode)?;
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.record_spend(debit, clock.unix_timestamp)?;
+    subscription.consume_discount();
+    // The delegation approved below covers the price of the next phase
+    subscription.advance_phase()?;
 
-    // Execute first payment upfront (No crank reward for self-execution)
-    crate::instructions::utils::execute_token_transfer(
-        &ctx.accounts.token_program,
-        &ctx.accounts.subscriber_token_account,
-        &ctx.accounts.treasury,
-        None, // No cranker for first payment
-        &ctx.accounts.accepted_mint,
-        &ctx.accounts.subscriber.to_account_info(), // Authority is the user
-        plan.amount, // Use plan.amount instead of subscription.amount to avoid borrow
-        0,           // No reward split
-        None,        // No seeds needed (direct user signature)
-    )?;
+    // Free tiers are never charged, so there is nothing to approve
+    if !ctx.accounts.subscription.is_free() {
+        // Approve the subscription PDA as delegate on subscriber's token account,
+        // covering any transfer fee the subscriber pays on the cranker and payee legs.
+        // Quote-priced subscriptions approve up to their cap, as the price moves
+        approve_delegation(
+            &ctx.accounts.service,
+            plan,
+            &ctx.accounts.subscription,
+            ctx.accounts.revenue_split.as_ref(),
+            &ctx.accounts.subscriber,
+            &ctx.accounts.subscriber_token_account,
+            &ctx.accounts.accepted_mint,
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    if first_payment > 0 {
+        // Execute first payment upfront (No crank reward for self-execution)
+        crate::instructions::utils::execute_token_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.subscriber_token_account,
+            &ctx.accounts.treasury,
+            None, // No cranker for first payment
+            &ctx.accounts.accepted_mint,
+            &ctx.accounts.subscriber.to_account_info(), // Authority is the user
+            first_payment,
+            0,    // No reward split
+            None, // No seeds needed (direct user signature)
+            ctx.accounts
+                .revenue_split
+                .as_deref()
+                .map(|split| (split, payees)),
+            plan.transfer_fee_mode,
+            hook_accounts,
+        )?;
+    }
 
     // Increment service subscriber count
     let service = &mut ctx.accounts.service;
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::errors::SolBillError;
+use crate::state::RewardPool;
+
+#[derive(Accounts)]
+pub struct DepositRewardPool<'info> {
+    /// Anyone may top up a service's reward pool.
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool", reward_pool.service.as_ref()],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<DepositRewardPool>, amount: u64) -> Result<()> {
+    require!(amount > 0, SolBillError::InvalidAmount);
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor.to_account_info(),
+                to: ctx.accounts.reward_pool.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Deposited {} lamports into reward pool of service {}",
+        amount,
+        ctx.accounts.reward_pool.service,
+    );
+    Ok(())
+}
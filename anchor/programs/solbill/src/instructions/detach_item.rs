@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{SubscriptionAccount, SubscriptionItem};
+
+#[derive(Accounts)]
+pub struct DetachItem<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The add-on to remove; its rent goes back to the subscriber.
+    #[account(
+        mut,
+        close = subscriber,
+        seeds = [b"item", subscription.key().as_ref(), item.plan.as_ref()],
+        bump = item.bump,
+        has_one = subscription,
+    )]
+    pub item: Account<'info, SubscriptionItem>,
+}
+
+pub fn handler(ctx: Context<DetachItem>) -> Result<()> {
+    let plan = ctx.accounts.item.plan;
+    let subscription = &mut ctx.accounts.subscription;
+    // Stops from the next charge; nothing already collected is refunded
+    subscription.items.retain(|item| item.plan != plan);
+
+    msg!(
+        "Add-on plan {} detached ({} add-ons left)",
+        plan,
+        subscription.items.len(),
+    );
+    Ok(())
+}
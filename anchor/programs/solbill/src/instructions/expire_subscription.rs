@@ -1,11 +1,15 @@
 use anchor_lang::prelude::*;
 
 use crate::errors::SolBillError;
-use crate::state::{PlanAccount, SubscriptionAccount, SubscriptionStatus};
+use crate::instructions::utils::{
+    close_subscription_dependents, pay_from_reward_pool, transfer_from_escrow,
+};
+use crate::state::{PlanAccount, RewardPool, SubscriptionAccount, SubscriptionStatus};
 
 #[derive(Accounts)]
 pub struct ExpireSubscription<'info> {
     /// Anyone can call this (permissionless crank).
+    #[account(mut)]
     pub cranker: Signer<'info>,
 
     #[account(
@@ -18,14 +22,48 @@ pub struct ExpireSubscription<'info> {
         mut,
         seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
         bump = subscription.bump,
-        close = cranker,
+        close = subscriber,
         constraint = subscription.plan == plan.key(),
         constraint = subscription.status == SubscriptionStatus::PastDue @ SolBillError::NotPastDue,
     )]
     pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// CHECK: The subscriber's wallet, which receives the subscription's rent.
+    #[account(
+        mut,
+        address = subscription.subscriber,
+    )]
+    pub subscriber: AccountInfo<'info>,
+
+    /// The service's reward pool, which pays the maintenance reward (if any).
+    #[account(
+        mut,
+        seeds = [b"reward_pool", plan.service.as_ref()],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+
+    /// The lamport escrow of a native SOL subscription, refunded to the subscriber.
+    #[account(
+        mut,
+        seeds = [b"escrow", subscription.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Option<SystemAccount<'info>>,
+
+    pub system_program: Option<Program<'info, System>>,
+    // remaining_accounts: any assigned `SeatAssignment`s (seats not passed are
+    // left stale), then every attached `SubscriptionItem`, closed alongside.
 }
 
-pub fn handler(ctx: Context<ExpireSubscription>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, ExpireSubscription<'info>>) -> Result<()> {
+    close_subscription_dependents(
+        &ctx.accounts.subscription,
+        ctx.remaining_accounts,
+        &ctx.accounts.subscriber,
+        false,
+    )?;
+
     let subscription = &mut ctx.accounts.subscription;
     let plan = &ctx.accounts.plan;
     let clock = Clock::get()?;
@@ -43,10 +81,41 @@ pub fn handler(ctx: Context<ExpireSubscription>) -> Result<()> {
 
     subscription.status = SubscriptionStatus::Expired;
 
+    // Hand any leftover escrow back along with the subscription's rent
+    if subscription.native_sol {
+        let (Some(escrow), Some(system_program)) = (
+            ctx.accounts.escrow.as_ref(),
+            ctx.accounts.system_program.as_ref(),
+        ) else {
+            return err!(SolBillError::PaymentMethodMismatch);
+        };
+        let subscription_key = subscription.key();
+        transfer_from_escrow(
+            system_program,
+            &escrow.to_account_info(),
+            &ctx.accounts.subscriber,
+            escrow.lamports(),
+            &[&[
+                b"escrow",
+                subscription_key.as_ref(),
+                &[ctx.bumps.escrow.unwrap_or_default()],
+            ]],
+        )?;
+    }
+
+    let mut reward = 0;
+    if let Some(pool) = ctx.accounts.reward_pool.as_mut() {
+        let lamports = pool.maintenance_reward;
+        if pay_from_reward_pool(pool, &ctx.accounts.cranker.to_account_info(), lamports)? {
+            reward = lamports;
+        }
+    }
+
     msg!(
-        "Subscription expired: {} (was past due since {})",
+        "Subscription expired: {} (was past due since {}, maintenance reward: {} lamports)",
         subscription.subscriber,
         subscription.next_billing_timestamp,
+        reward,
     );
     Ok(())
 }
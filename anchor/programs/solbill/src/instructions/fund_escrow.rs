@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::errors::SolBillError;
+use crate::state::SubscriptionAccount;
+
+#[derive(Accounts)]
+pub struct FundEscrow<'info> {
+    /// Anyone may top up a subscription's escrow.
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(
+        seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        constraint = subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", subscription.key().as_ref()],
+        bump,
+    )]
+    pub escrow: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<FundEscrow>, amount: u64) -> Result<()> {
+    require!(amount > 0, SolBillError::InvalidAmount);
+    require!(
+        ctx.accounts.escrow.lamports().saturating_add(amount) >= Rent::get()?.minimum_balance(0),
+        SolBillError::InsufficientFunds
+    );
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.funder.to_account_info(),
+                to: ctx.accounts.escrow.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    msg!(
+        "Deposited {} lamports into escrow of subscription {}",
+        amount,
+        ctx.accounts.subscription.key(),
+    );
+    Ok(())
+}
@@ -43,6 +43,11 @@ pub fn handler(ctx: Context<InitializeService>) -> Result<()> {
     service.plan_count = 0;
     service.subscriber_count = 0;
     service.created_at = clock.unix_timestamp;
+    service.revenue_split = None;
+    service.reward_pool = None;
+    service.collector = None;
+    service.sol_treasury = None;
+    service.subscriptions_created = 0;
     service.bump = ctx.bumps.service;
 
     msg!("Service initialized by {}", service.authority);
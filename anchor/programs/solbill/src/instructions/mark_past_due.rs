@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Owners;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::errors::SolBillError;
+use crate::instructions::utils::{can_collect, escrow_available_lamports, pay_from_reward_pool};
+use crate::state::{RewardPool, SubscriptionAccount, SubscriptionStatus};
+
+#[derive(Accounts)]
+pub struct MarkPastDue<'info> {
+    /// Anyone can call this (permissionless crank).
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscription.subscriber.as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// CHECK: The subscriber's token account (checked for funds and delegation),
+    /// or the lamport escrow of a native SOL subscription.
+    #[account(
+        address = subscription.subscriber_token_account,
+    )]
+    pub payment_source: UncheckedAccount<'info>,
+
+    /// The service's reward pool, which pays the maintenance reward (if any).
+    #[account(
+        mut,
+        seeds = [b"reward_pool", subscription.service.as_ref()],
+        bump = reward_pool.bump,
+    )]
+    pub reward_pool: Option<Account<'info, RewardPool>>,
+}
+
+pub fn handler(ctx: Context<MarkPastDue>) -> Result<()> {
+    let clock = Clock::get()?;
+    let subscription_key = ctx.accounts.subscription.key();
+    let subscription = &mut ctx.accounts.subscription;
+
+    require!(
+        clock.unix_timestamp >= subscription.next_billing_timestamp,
+        SolBillError::BillingNotDue,
+    );
+
+    // Only delinquent subscriptions may be flagged: the charge must be
+    // uncollectable because of missing funds or a revoked/short delegation,
+    // or blocked on reapproving the delegation after a phase price rise.
+    let collectable = if subscription.reapproval_required {
+        false
+    } else if subscription.native_sol {
+        escrow_available_lamports(&ctx.accounts.payment_source)?
+            >= subscription.next_native_charge()?
+    } else {
+        let source = &ctx.accounts.payment_source;
+        require!(
+            TokenAccount::owners().contains(source.owner),
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        let token_account = TokenAccount::try_deserialize(&mut &source.try_borrow_data()?[..])?;
+        // A quote-priced charge's token amount depends on the price, so only
+        // a source unable to pay anything at all counts as delinquent
+        let required = if subscription.quote_pricing.is_some() {
+            1
+        } else {
+            subscription.next_charge()?
+        };
+        can_collect(&token_account, &subscription_key, required)
+    };
+    require!(!collectable, SolBillError::PaymentStillCollectable);
+
+    subscription.status = SubscriptionStatus::PastDue;
+
+    let mut reward = 0;
+    if let Some(pool) = ctx.accounts.reward_pool.as_mut() {
+        let lamports = pool.maintenance_reward;
+        if pay_from_reward_pool(pool, &ctx.accounts.cranker.to_account_info(), lamports)? {
+            reward = lamports;
+        }
+    }
+
+    msg!(
+        "Subscription past due: {} (due since {}, maintenance reward: {} lamports)",
+        subscription.subscriber,
+        subscription.next_billing_timestamp,
+        reward,
+    );
+    Ok(())
+}
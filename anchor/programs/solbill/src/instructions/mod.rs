@@ -1,20 +1,94 @@
+pub mod add_member;
+pub mod add_one_off_charge;
+pub mod attach_item;
+pub mod cancel_native_subscription;
 pub mod cancel_subscription;
 pub mod change_plan;
+pub mod check_access;
+pub mod close_revenue_split;
+pub mod collect_native_payment;
 pub mod collect_payment;
+pub mod collect_payments_batch;
+pub mod create_coupon;
+pub mod create_native_subscription;
 pub mod create_plan;
+pub mod create_revenue_split;
+pub mod create_reward_pool;
 pub mod create_subscription;
+pub mod deposit_reward_pool;
+pub mod detach_item;
 pub mod expire_subscription;
+pub mod fund_escrow;
 pub mod initialize_service;
+pub mod mark_past_due;
+pub mod reapprove_delegation;
+pub mod register_affiliate;
+pub mod remove_member;
+pub mod set_collector;
+pub mod set_exclusive_window;
+pub mod set_late_fee;
+pub mod set_loyalty_tiers;
+pub mod set_pay_what_you_want;
+pub mod set_plan_currency;
+pub mod set_price_phases;
+pub mod set_quote_pricing;
+pub mod set_reward_policy;
+pub mod set_seat_limits;
+pub mod set_setup_fee;
+pub mod set_sol_treasury;
+pub mod set_spending_caps;
+pub mod set_transfer_fee_mode;
+pub mod update_chosen_amount;
 pub mod update_plan;
+pub mod update_quantity;
+pub mod update_reward_pool;
+pub mod withdraw_reward_pool;
 
 #[allow(ambiguous_glob_reexports)]
+pub use add_member::*;
+pub use add_one_off_charge::*;
+pub use attach_item::*;
+pub use cancel_native_subscription::*;
 pub use cancel_subscription::*;
 pub use change_plan::*;
+pub use check_access::*;
+pub use close_revenue_split::*;
+pub use collect_native_payment::*;
 pub use collect_payment::*;
+pub use collect_payments_batch::*;
+pub use create_coupon::*;
+pub use create_native_subscription::*;
 pub use create_plan::*;
+pub use create_revenue_split::*;
+pub use create_reward_pool::*;
 pub use create_subscription::*;
+pub use deposit_reward_pool::*;
+pub use detach_item::*;
 pub use expire_subscription::*;
+pub use fund_escrow::*;
 pub use initialize_service::*;
+pub use mark_past_due::*;
+pub use reapprove_delegation::*;
+pub use register_affiliate::*;
+pub use remove_member::*;
+pub use set_collector::*;
+pub use set_exclusive_window::*;
+pub use set_late_fee::*;
+pub use set_loyalty_tiers::*;
+pub use set_pay_what_you_want::*;
+pub use set_plan_currency::*;
+pub use set_price_phases::*;
+pub use set_quote_pricing::*;
+pub use set_reward_policy::*;
+pub use set_seat_limits::*;
+pub use set_setup_fee::*;
+pub use set_sol_treasury::*;
+pub use set_spending_caps::*;
+pub use set_transfer_fee_mode::*;
+pub use update_chosen_amount::*;
 pub use update_plan::*;
+pub use update_quantity::*;
+pub use update_reward_pool::*;
+pub use withdraw_reward_pool::*;
 
 pub mod utils;
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::SolBillError;
+use crate::instructions::utils::approve_delegation;
+use crate::state::{
+    PlanAccount, RevenueSplit, ServiceAccount, SubscriptionAccount, SubscriptionStatus,
+};
+
+#[derive(Accounts)]
+pub struct ReapproveDelegation<'info> {
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The subscription's current plan (resolves the revenue split).
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        has_one = service,
+        constraint = subscription.status == SubscriptionStatus::Active
+            || subscription.status == SubscriptionStatus::PastDue @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscriber's token account.
+    #[account(
+        mut,
+        address = subscription.subscriber_token_account,
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The subscription's mint (checked for a transfer fee).
+    #[account(
+        address = subscriber_token_account.mint,
+    )]
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The revenue split configured on the plan or service, if any; the
+    /// allowance covers one transfer per recipient.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+}
+
+pub fn handler(ctx: Context<ReapproveDelegation>) -> Result<()> {
+    // Approve the next charge at the current phase's price, as create_subscription does
+    let allowance = approve_delegation(
+        &ctx.accounts.service,
+        &ctx.accounts.plan,
+        &ctx.accounts.subscription,
+        ctx.accounts.revenue_split.as_ref(),
+        &ctx.accounts.subscriber,
+        &ctx.accounts.subscriber_token_account,
+        &ctx.accounts.accepted_mint,
+        &ctx.accounts.token_program,
+    )?;
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.reapproval_required = false;
+
+    msg!(
+        "Delegation re-approved: {} tokens for phase {}",
+        allowance,
+        subscription.phase_index,
+    );
+    Ok(())
+}
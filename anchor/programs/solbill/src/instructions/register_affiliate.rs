@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::errors::SolBillError;
+use crate::state::{Affiliate, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct RegisterAffiliate<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// CHECK: The affiliate's wallet; only used as a PDA seed and payout owner.
+    pub wallet: UncheckedAccount<'info>,
+
+    /// Token account (of the service's accepted mint) receiving the rewards.
+    #[account(
+        token::mint = service.accepted_mint,
+        token::authority = wallet,
+    )]
+    pub payout_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Affiliate::INIT_SPACE,
+        seeds = [b"affiliate", service.key().as_ref(), wallet.key().as_ref()],
+        bump,
+    )]
+    pub affiliate: Account<'info, Affiliate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RegisterAffiliate>, reward_bps: u16, reward_cycles: u32) -> Result<()> {
+    let affiliate = &mut ctx.accounts.affiliate;
+    affiliate.service = ctx.accounts.service.key();
+    affiliate.wallet = ctx.accounts.wallet.key();
+    affiliate.payout_token_account = ctx.accounts.payout_token_account.key();
+    affiliate.reward_bps = reward_bps;
+    affiliate.reward_cycles = reward_cycles;
+    affiliate.referrals = 0;
+    affiliate.total_rewards = 0;
+    affiliate.total_referred_revenue = 0;
+    affiliate.bump = ctx.bumps.affiliate;
+    affiliate.validate()?;
+
+    msg!(
+        "Affiliate registered: {} ({} bps for {} collections)",
+        affiliate.wallet,
+        reward_bps,
+        reward_cycles,
+    );
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{SeatAssignment, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct RemoveMember<'info> {
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The seat to free; its rent goes back to the subscriber.
+    #[account(
+        mut,
+        close = subscriber,
+        seeds = [b"seat", subscription.key().as_ref(), seat_assignment.member.as_ref()],
+        bump = seat_assignment.bump,
+        has_one = subscription,
+    )]
+    pub seat_assignment: Account<'info, SeatAssignment>,
+}
+
+pub fn handler(ctx: Context<RemoveMember>) -> Result<()> {
+    let subscription = &mut ctx.accounts.subscription;
+    // A stale seat from an earlier subscription at this address isn't counted
+    if ctx.accounts.seat_assignment.generation == subscription.generation {
+        subscription.assigned_seats = subscription.assigned_seats.saturating_sub(1);
+    }
+
+    msg!(
+        "Seat freed from {} ({}/{} seats)",
+        ctx.accounts.seat_assignment.member,
+        subscription.assigned_seats,
+        subscription.quantity,
+    );
+    Ok(())
+}
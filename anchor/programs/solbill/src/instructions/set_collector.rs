@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::ServiceAccount;
+
+#[derive(Accounts)]
+pub struct SetCollector<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+}
+
+pub fn handler(ctx: Context<SetCollector>, collector: Option<Pubkey>) -> Result<()> {
+    let service = &mut ctx.accounts.service;
+    service.collector = collector;
+
+    match collector {
+        Some(key) => msg!("Designated collector set to {}", key),
+        None => msg!("Designated collector cleared"),
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetExclusiveWindow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetExclusiveWindow>, exclusive_window: i64) -> Result<()> {
+    let plan = &mut ctx.accounts.plan;
+
+    // A window as long as the interval would lock public crankers out for good
+    require!(
+        exclusive_window >= 0
+            && exclusive_window < plan.interval
+            && plan
+                .price_phases
+                .iter()
+                .all(|phase| exclusive_window < phase.interval),
+        SolBillError::InvalidExclusiveWindow
+    );
+    plan.exclusive_window = exclusive_window;
+
+    msg!(
+        "Plan {} exclusive collection window set to {}s",
+        plan.plan_index,
+        exclusive_window,
+    );
+    Ok(())
+}
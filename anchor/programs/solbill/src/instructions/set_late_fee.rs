@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{LateFee, PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetLateFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetLateFee>, late_fee: Option<LateFee>) -> Result<()> {
+    if let Some(late_fee) = &late_fee {
+        late_fee.validate()?;
+    }
+
+    let plan = &mut ctx.accounts.plan;
+    // A zero fee removes the late fee
+    plan.late_fee = late_fee.filter(LateFee::is_enabled);
+
+    msg!(
+        "Plan {} late fee {}",
+        plan.plan_index,
+        if plan.late_fee.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        },
+    );
+    Ok(())
+}
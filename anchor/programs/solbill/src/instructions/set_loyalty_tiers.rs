@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{LoyaltyTier, PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetLoyaltyTiers<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetLoyaltyTiers>, tiers: Vec<LoyaltyTier>) -> Result<()> {
+    LoyaltyTier::validate_all(&tiers)?;
+
+    let plan = &mut ctx.accounts.plan;
+    plan.loyalty_tiers = tiers;
+
+    msg!(
+        "Plan {} loyalty tiers set ({} tiers)",
+        plan.plan_index,
+        plan.loyalty_tiers.len(),
+    );
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{AmountRange, PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetPayWhatYouWant<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+        constraint = !plan.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetPayWhatYouWant>, range: Option<AmountRange>) -> Result<()> {
+    let plan = &mut ctx.accounts.plan;
+
+    if let Some(range) = &range {
+        range.validate()?;
+        require!(
+            plan.price_phases.is_empty(),
+            SolBillError::PricingModeConflict
+        );
+        // Subscribers who don't choose pay the floor, so the crank rewards
+        // must stay below it as `update_plan` requires of the amount
+        require!(
+            plan.crank_reward < range.min_amount,
+            SolBillError::InvalidCrankReward
+        );
+        if let Some(policy) = &plan.percentage_reward {
+            policy.validate(range.min_amount)?;
+        }
+        if let Some(escalation) = &plan.reward_escalation {
+            escalation.validate(range.min_amount)?;
+        }
+        plan.amount = range.min_amount;
+    }
+    plan.pay_what_you_want = range;
+
+    match range {
+        Some(range) => msg!(
+            "Plan {} is pay-what-you-want: {} minimum, {} maximum",
+            plan.plan_index,
+            range.min_amount,
+            range.max_amount,
+        ),
+        None => msg!("Plan {} returned to fixed pricing", plan.plan_index),
+    }
+    Ok(())
+}
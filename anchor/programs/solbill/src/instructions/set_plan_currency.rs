@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetPlanCurrency<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The plan to price in `accepted_mint`. Its own revenue split must be
+    /// closed first, since the payees hold the old mint.
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+        constraint = plan.revenue_split.is_none() @ SolBillError::RevenueSplitMismatch,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    /// The SPL token mint this plan is paid in.
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    /// The merchant's token account receiving this plan's payments.
+    #[account(
+        token::mint = accepted_mint,
+        token::authority = authority,
+        token::token_program = token_program,
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn handler(ctx: Context<SetPlanCurrency>) -> Result<()> {
+    let service = &ctx.accounts.service;
+    let mint = ctx.accounts.accepted_mint.key();
+    let treasury = ctx.accounts.treasury.key();
+    let plan = &mut ctx.accounts.plan;
+
+    // Pointing back at the service's own pair clears the override
+    if mint == service.accepted_mint && treasury == service.treasury {
+        plan.accepted_mint = None;
+        plan.treasury = None;
+    } else {
+        plan.accepted_mint = Some(mint);
+        plan.treasury = Some(treasury);
+    }
+
+    msg!(
+        "Plan {} now paid in {} to treasury {}",
+        plan.plan_index,
+        mint,
+        treasury,
+    );
+    Ok(())
+}
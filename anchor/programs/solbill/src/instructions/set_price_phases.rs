@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, PricePhase, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetPricePhases<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetPricePhases>, phases: Vec<PricePhase>) -> Result<()> {
+    PricePhase::validate_all(&phases)?;
+
+    let plan = &mut ctx.accounts.plan;
+    require!(
+        phases
+            .iter()
+            .all(|phase| phase.interval > plan.exclusive_window),
+        SolBillError::InvalidExclusiveWindow
+    );
+    require!(
+        phases.is_empty() || plan.pay_what_you_want.is_none(),
+        SolBillError::PricingModeConflict
+    );
+    // The plan advertises (and subscribers first pay) the opening phase
+    if let Some(first) = phases.first() {
+        plan.amount = first.amount;
+        plan.interval = first.interval;
+    }
+    plan.price_phases = phases;
+    // Every phase must still leave the merchant something after the reward
+    plan.validate_rewards(plan.lowest_amount())?;
+
+    msg!(
+        "Plan {} price phases set ({} phases)",
+        plan.plan_index,
+        plan.price_phases.len(),
+    );
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, QuotePricing, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetQuotePricing<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+        constraint = !plan.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    /// CHECK: Price account named in `pricing`; owner and layout are verified
+    /// by the price reader. Omit when clearing quote pricing.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+}
+
+pub fn handler(ctx: Context<SetQuotePricing>, pricing: Option<QuotePricing>) -> Result<()> {
+    if let Some(pricing) = &pricing {
+        pricing.validate()?;
+        let feed = ctx
+            .accounts
+            .price_feed
+            .as_ref()
+            .ok_or(SolBillError::InvalidPriceFeed)?;
+        require_keys_eq!(
+            feed.key(),
+            pricing.price_feed,
+            SolBillError::InvalidPriceFeed
+        );
+        pricing.read_price(feed)?;
+    }
+
+    let plan = &mut ctx.accounts.plan;
+    plan.quote_pricing = pricing;
+
+    msg!(
+        "Plan {} quote pricing {}",
+        plan.plan_index,
+        if pricing.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        },
+    );
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PercentageReward, PlanAccount, RewardEscalation, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetRewardPolicy<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(
+    ctx: Context<SetRewardPolicy>,
+    percentage_reward: Option<PercentageReward>,
+    reward_escalation: Option<RewardEscalation>,
+) -> Result<()> {
+    let plan = &mut ctx.accounts.plan;
+    plan.percentage_reward = percentage_reward;
+    plan.reward_escalation = reward_escalation;
+    plan.validate_rewards(plan.lowest_amount())?;
+
+    msg!(
+        "Plan {} reward policy set — percentage: {}, escalation: {}",
+        plan.plan_index,
+        plan.percentage_reward.is_some(),
+        plan.reward_escalation.is_some(),
+    );
+    Ok(())
+}
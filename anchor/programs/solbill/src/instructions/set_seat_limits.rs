@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetSeatLimits<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetSeatLimits>, min_quantity: u32, max_quantity: u32) -> Result<()> {
+    require!(
+        min_quantity > 0 && (max_quantity == 0 || max_quantity >= min_quantity),
+        SolBillError::InvalidSeatLimits
+    );
+
+    let plan = &mut ctx.accounts.plan;
+    plan.min_quantity = min_quantity;
+    plan.max_quantity = max_quantity;
+
+    msg!(
+        "Plan {} seat limits set: {} minimum, {} maximum",
+        plan.plan_index,
+        min_quantity,
+        max_quantity,
+    );
+    Ok(())
+}
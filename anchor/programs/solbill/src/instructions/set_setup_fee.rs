@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct SetSetupFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetSetupFee>, setup_fee: u64) -> Result<()> {
+    let plan = &mut ctx.accounts.plan;
+    require!(
+        setup_fee == 0 || !plan.is_free(),
+        SolBillError::FreeSubscription
+    );
+    plan.setup_fee = setup_fee;
+
+    msg!("Plan {} setup fee set to {}", plan.plan_index, setup_fee);
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::ServiceAccount;
+
+#[derive(Accounts)]
+pub struct SetSolTreasury<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+}
+
+pub fn handler(ctx: Context<SetSolTreasury>, sol_treasury: Option<Pubkey>) -> Result<()> {
+    let service = &mut ctx.accounts.service;
+    service.sol_treasury = sol_treasury;
+
+    match sol_treasury {
+        Some(key) => msg!("Native SOL billing enabled, paying {}", key),
+        None => msg!("Native SOL billing disabled"),
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{SpendingCaps, SubscriptionAccount};
+
+#[derive(Accounts)]
+pub struct SetSpendingCaps<'info> {
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+}
+
+pub fn handler(ctx: Context<SetSpendingCaps>, caps: SpendingCaps) -> Result<()> {
+    caps.validate()?;
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.spending_caps = caps;
+
+    msg!(
+        "Spending caps set: {} per charge, {} per {}s, {} lifetime ({} spent)",
+        caps.per_charge,
+        caps.per_period,
+        caps.period,
+        caps.lifetime,
+        subscription.lifetime_spent,
+    );
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{PlanAccount, ServiceAccount, TransferFeeMode};
+
+#[derive(Accounts)]
+pub struct SetTransferFeeMode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"plan", service.key().as_ref(), plan.plan_index.to_le_bytes().as_ref()],
+        bump = plan.bump,
+        has_one = service,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+}
+
+pub fn handler(ctx: Context<SetTransferFeeMode>, transfer_fee_mode: TransferFeeMode) -> Result<()> {
+    let plan = &mut ctx.accounts.plan;
+    plan.transfer_fee_mode = transfer_fee_mode;
+
+    msg!(
+        "Plan {} transfer fees paid by the {}",
+        plan.plan_index,
+        match transfer_fee_mode {
+            TransferFeeMode::MerchantAbsorbs => "merchant",
+            TransferFeeMode::SubscriberPays => "subscriber",
+        },
+    );
+    Ok(())
+}
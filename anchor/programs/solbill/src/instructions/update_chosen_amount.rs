@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::SolBillError;
+use crate::instructions::utils::approve_delegation;
+use crate::state::{
+    PlanAccount, RevenueSplit, ServiceAccount, SubscriptionAccount, SubscriptionStatus,
+};
+
+#[derive(Accounts)]
+pub struct UpdateChosenAmount<'info> {
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The subscription's current plan.
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        has_one = service,
+        constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+        constraint = !subscription.from_offer @ SolBillError::PricingModeConflict,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscriber's token account.
+    #[account(
+        mut,
+        address = subscription.subscriber_token_account,
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The subscription's mint (checked for a transfer fee).
+    #[account(
+        address = subscriber_token_account.mint,
+    )]
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The revenue split configured on the plan or service, if any; the
+    /// allowance covers one transfer per recipient.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+}
+
+pub fn handler(ctx: Context<UpdateChosenAmount>, amount: u64) -> Result<()> {
+    let range = ctx
+        .accounts
+        .plan
+        .pay_what_you_want
+        .ok_or(SolBillError::AmountOutOfRange)?;
+    range.check(amount)?;
+
+    let subscription = &mut ctx.accounts.subscription;
+    subscription.amount = amount;
+    let recurring = subscription.recurring_amount()?;
+    if subscription.quote_pricing.is_none() {
+        subscription
+            .spending_caps
+            .admits(subscription.discounted(recurring)?)?;
+    }
+
+    // Re-approve the delegation for the new amount, charged from the next
+    // collection on. Past-due subscriptions can't change it, so an overdue
+    // charge and its late fee stay at the amount that was missed
+    approve_delegation(
+        &ctx.accounts.service,
+        &ctx.accounts.plan,
+        &ctx.accounts.subscription,
+        ctx.accounts.revenue_split.as_ref(),
+        &ctx.accounts.subscriber,
+        &ctx.accounts.subscriber_token_account,
+        &ctx.accounts.accepted_mint,
+        &ctx.accounts.token_program,
+    )?;
+
+    msg!(
+        "Chosen amount updated: {} pays {} per cycle",
+        ctx.accounts.subscriber.key(),
+        amount,
+    );
+    Ok(())
+}
@@ -33,15 +33,22 @@ pub fn handler(
 ) -> Result<()> {
     let plan = &mut ctx.accounts.plan;
 
+    // Phases and pay-what-you-want set the amount themselves, and phases
+    // their own intervals
+    require!(
+        new_amount.is_none() || (plan.price_phases.is_empty() && plan.pay_what_you_want.is_none()),
+        SolBillError::PricingModeConflict
+    );
+    require!(
+        new_interval.is_none() || plan.price_phases.is_empty(),
+        SolBillError::PricingModeConflict
+    );
+
+    // A zero amount makes a free tier, which never pays a crank reward
     if let Some(amount) = new_amount {
-        require!(amount > 0, SolBillError::InvalidAmount);
         plan.amount = amount;
     }
     if let Some(cranker_reward) = new_cranker_reward {
-        require!(
-            cranker_reward < plan.amount,
-            SolBillError::InvalidCrankReward
-        );
         plan.crank_reward = cranker_reward;
     }
     if let Some(interval) = new_interval {
@@ -54,6 +61,17 @@ pub fn handler(
     if let Some(grace_period) = new_grace_period {
         plan.grace_period = grace_period;
     }
+    plan.validate_rewards(plan.lowest_amount())?;
+    // Free tiers are never charged, not even an activation fee
+    require!(
+        !plan.is_free() || plan.setup_fee == 0,
+        SolBillError::FreeSubscription
+    );
+    // A window as long as the interval would lock public crankers out for good
+    require!(
+        plan.exclusive_window < plan.interval,
+        SolBillError::InvalidExclusiveWindow
+    );
 
     msg!(
         "Plan {} updated — amount: {}, reward: {}, interval: {}s, active: {}",
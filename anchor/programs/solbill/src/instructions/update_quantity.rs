@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::errors::SolBillError;
+use crate::events::QuantityUpdated;
+use crate::instructions::utils::approve_delegation;
+use crate::state::{
+    PlanAccount, RevenueSplit, ServiceAccount, SubscriptionAccount, SubscriptionStatus,
+};
+
+#[derive(Accounts)]
+pub struct UpdateQuantity<'info> {
+    pub subscriber: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", service.authority.as_ref()],
+        bump = service.bump,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    /// The subscription's current plan (source of the seat limits).
+    #[account(
+        address = subscription.plan,
+    )]
+    pub plan: Account<'info, PlanAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"subscription", subscriber.key().as_ref(), subscription.original_plan.as_ref()],
+        bump = subscription.bump,
+        has_one = subscriber,
+        has_one = service,
+        constraint = subscription.status == SubscriptionStatus::Active @ SolBillError::SubscriptionNotActive,
+        constraint = !subscription.native_sol @ SolBillError::PaymentMethodMismatch,
+    )]
+    pub subscription: Account<'info, SubscriptionAccount>,
+
+    /// The subscriber's token account.
+    #[account(
+        mut,
+        address = subscription.subscriber_token_account,
+    )]
+    pub subscriber_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The subscription's mint (checked for a transfer fee).
+    #[account(
+        address = subscriber_token_account.mint,
+    )]
+    pub accepted_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The revenue split configured on the plan or service, if any; the
+    /// allowance covers one transfer per recipient.
+    pub revenue_split: Option<Account<'info, RevenueSplit>>,
+}
+
+pub fn handler(ctx: Context<UpdateQuantity>, quantity: u32) -> Result<()> {
+    ctx.accounts.plan.check_quantity(quantity)?;
+    require!(
+        quantity >= ctx.accounts.subscription.assigned_seats,
+        SolBillError::SeatsInUse
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let subscription_key = ctx.accounts.subscription.key();
+    let subscription = &mut ctx.accounts.subscription;
+    let previous = subscription.quantity;
+
+    // Seats added or removed mid-period are charged or credited for the rest
+    // of it. Quote-priced amounts need a price to convert, so those changes
+    // take effect from the next cycle instead
+    let (prorated_charge, prorated_credit) = if subscription.quote_pricing.is_some() {
+        (0, 0)
+    } else if quantity > previous {
+        (subscription.prorate(quantity - previous, now)?, 0)
+    } else {
+        (0, subscription.prorate(previous - quantity, now)?)
+    };
+    subscription.pending_charges = subscription
+        .pending_charges
+        .checked_add(prorated_charge)
+        .ok_or(SolBillError::Overflow)?;
+    let offset = prorated_credit.min(subscription.pending_charges);
+    subscription.pending_charges -= offset;
+    subscription.credit_balance = subscription
+        .credit_balance
+        .checked_add(prorated_credit - offset)
+        .ok_or(SolBillError::Overflow)?;
+    subscription.quantity = quantity;
+
+    let recurring = subscription.recurring_amount()?;
+    if subscription.quote_pricing.is_none() {
+        subscription
+            .spending_caps
+            .admits(subscription.discounted(recurring)?)?;
+    }
+
+    // Re-approve the delegation for the new seat count and any prorated charge
+    approve_delegation(
+        &ctx.accounts.service,
+        &ctx.accounts.plan,
+        &ctx.accounts.subscription,
+        ctx.accounts.revenue_split.as_ref(),
+        &ctx.accounts.subscriber,
+        &ctx.accounts.subscriber_token_account,
+        &ctx.accounts.accepted_mint,
+        &ctx.accounts.token_program,
+    )?;
+
+    msg!(
+        "Quantity updated: {} -> {} seats (charge {}, credit {})",
+        previous,
+        quantity,
+        prorated_charge,
+        prorated_credit,
+    );
+    emit!(QuantityUpdated {
+        subscription: subscription_key,
+        quantity,
+        prorated_charge,
+        prorated_credit,
+    });
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{RewardPool, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct UpdateRewardPool<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump = reward_pool.bump,
+        has_one = service,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+}
+
+pub fn handler(
+    ctx: Context<UpdateRewardPool>,
+    lamports_per_crank: u64,
+    maintenance_reward: u64,
+) -> Result<()> {
+    require!(lamports_per_crank > 0, SolBillError::InvalidAmount);
+
+    let reward_pool = &mut ctx.accounts.reward_pool;
+    reward_pool.lamports_per_crank = lamports_per_crank;
+    reward_pool.maintenance_reward = maintenance_reward;
+
+    msg!(
+        "Reward pool updated — {} lamports per crank, {} per maintenance crank",
+        lamports_per_crank,
+        maintenance_reward,
+    );
+    Ok(())
+}
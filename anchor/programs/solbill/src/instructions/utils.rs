@@ -1,10 +1,50 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::get_instruction_relative;
+use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+};
 use anchor_spl::token_interface::{
-    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+    approve, transfer_checked, Approve, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
 
 use crate::errors::SolBillError;
+use crate::oracle::PriceData;
+use crate::state::{
+    PlanAccount, QuotePricing, RevenueSplit, RewardPool, SeatAssignment, ServiceAccount,
+    SubscriptionAccount, SubscriptionItem, TransferFeeMode,
+};
 
+/// Revenue split to apply to the net payment, with the recipients' token
+/// accounts (in `RevenueSplit::recipients` order).
+pub type SplitPayees<'a, 'info> = (&'a RevenueSplit, &'a [AccountInfo<'info>]);
+
+/// Split an instruction's `remaining_accounts` into the revenue split payees
+/// (first, in recipient order) and the transfer-hook accounts that follow:
+/// the hook program, its extra-account-metas validation account and the extra
+/// accounts it lists.
+pub fn split_remaining_accounts<'a, 'info>(
+    remaining: &'a [AccountInfo<'info>],
+    revenue_split: Option<&RevenueSplit>,
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    let payees = revenue_split.map_or(0, |split| split.recipients.len());
+    require!(
+        remaining.len() >= payees,
+        SolBillError::InvalidSplitRecipient
+    );
+    Ok(remaining.split_at(payees))
+}
+
+/// Pay `amount` out of `from`: `crank_reward` to the cranker and the rest to
+/// the treasury (or the split payees). With a Token-2022 transfer fee, the
+/// cranker's reward is always grossed up so it arrives in full; `fee_mode`
+/// decides whether the payees' fees come out of their share or are added on
+/// top of the subscriber's charge. `hook_accounts` are forwarded to every
+/// transfer for mints with a transfer hook. Returns the total fee withheld by
+/// the mint.
+#[allow(clippy::too_many_arguments)]
 pub fn execute_token_transfer<'info>(
     token_program: &Interface<'info, TokenInterface>,
     from: &InterfaceAccount<'info, TokenAccount>,
@@ -15,41 +55,260 @@ pub fn execute_token_transfer<'info>(
     amount: u64,
     crank_reward: u64,
     signer_seeds: Option<&[&[&[u8]]]>,
-) -> Result<()> {
-    // Calculate treasury amount first
-    let treasury_amount = if to_cranker.is_some() && crank_reward > 0 {
-        amount
-            .checked_sub(crank_reward)
-            .ok_or(SolBillError::Overflow)?
+    revenue_split: Option<SplitPayees<'_, 'info>>,
+    fee_mode: TransferFeeMode,
+    hook_accounts: &[AccountInfo<'info>],
+) -> Result<u64> {
+    let fee = TransferFeeSchedule::load(&mint.to_account_info())?;
+    let crank_reward = if to_cranker.is_some() {
+        crank_reward
     } else {
-        amount
+        0
+    };
+
+    // Work out what each payee is sent before moving anything
+    let (cranker_gross, payee_amounts) = fee.transfer_legs(
+        amount,
+        crank_reward,
+        revenue_split.map(|(split, _)| split),
+        fee_mode,
+    )?;
+
+    let mut withheld = 0u64;
+    let mut send = |to: &AccountInfo<'info>, gross: u64| -> Result<()> {
+        if gross == 0 {
+            return Ok(());
+        }
+        withheld = withheld
+            .checked_add(fee.fee_on(gross)?)
+            .ok_or(SolBillError::Overflow)?;
+        transfer_tokens(
+            token_program,
+            from,
+            to,
+            mint,
+            authority,
+            gross,
+            signer_seeds,
+            hook_accounts,
+        )
     };
 
     // 1. Pay the Cranker their reward (if applicable)
     if let Some(cranker_acc) = to_cranker {
-        if crank_reward > 0 {
-            let cpi_accounts = TransferChecked {
-                from: from.to_account_info(),
-                to: cranker_acc.to_account_info(),
-                authority: authority.clone(),
-                mint: mint.to_account_info(),
-            };
-            let cpi_p = token_program.to_account_info();
-
-            if let Some(seeds) = signer_seeds {
-                let cpi_ctx = CpiContext::new_with_signer(cpi_p, cpi_accounts, seeds);
-                transfer_checked(cpi_ctx, crank_reward, mint.decimals)?;
-            } else {
-                let cpi_ctx = CpiContext::new(cpi_p, cpi_accounts);
-                transfer_checked(cpi_ctx, crank_reward, mint.decimals)?;
+        send(&cranker_acc.to_account_info(), cranker_gross)?;
+    }
+
+    // 2. Transfer remainder to Treasury, or across the split payees
+    match revenue_split {
+        Some((split, payees)) => {
+            require!(
+                payees.len() >= split.recipients.len(),
+                SolBillError::InvalidSplitRecipient
+            );
+            for ((recipient, payee), gross) in split
+                .recipients
+                .iter()
+                .zip(payees.iter())
+                .zip(payee_amounts)
+            {
+                require_keys_eq!(
+                    payee.key(),
+                    recipient.token_account,
+                    SolBillError::InvalidSplitRecipient
+                );
+                send(payee, gross)?;
             }
         }
+        None => {
+            send(&to_treasury.to_account_info(), payee_amounts[0])?;
+        }
+    }
+
+    Ok(withheld)
+}
+
+/// Token-2022 transfer fee of a mint, resolved for the current epoch.
+/// Mints without the TransferFee extension charge nothing.
+pub struct TransferFeeSchedule {
+    config: Option<TransferFeeConfig>,
+    epoch: u64,
+}
+
+impl TransferFeeSchedule {
+    pub fn load(mint: &AccountInfo) -> Result<Self> {
+        let config = if *mint.owner == spl_token_2022::ID {
+            let data = mint.try_borrow_data()?;
+            let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+            state.get_extension::<TransferFeeConfig>().ok().copied()
+        } else {
+            None
+        };
+        Ok(Self {
+            config,
+            epoch: Clock::get()?.epoch,
+        })
+    }
+
+    /// Whether the mint has a transfer fee configured at all.
+    pub fn has_fee(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Fee withheld when sending `gross`.
+    pub fn fee_on(&self, gross: u64) -> Result<u64> {
+        match &self.config {
+            Some(config) => config
+                .calculate_epoch_fee(self.epoch, gross)
+                .ok_or_else(|| error!(SolBillError::Overflow)),
+            None => Ok(0),
+        }
+    }
+
+    /// Amount that arrives when `gross` is sent.
+    pub fn net_of(&self, gross: u64) -> Result<u64> {
+        Ok(gross - self.fee_on(gross)?)
+    }
+
+    /// Amount to send so that `net` arrives after the fee.
+    pub fn gross_up(&self, net: u64) -> Result<u64> {
+        match &self.config {
+            Some(config) => config
+                .get_epoch_fee(self.epoch)
+                .calculate_pre_fee_amount(net)
+                .ok_or_else(|| error!(SolBillError::Overflow)),
+            None => Ok(net),
+        }
+    }
+
+    /// The largest crank reward, up to `reward`, that a payment of `amount`
+    /// can carry. The cranker's leg is grossed up for the fee; when the
+    /// merchant absorbs it, that leg comes out of `amount`, so a reward whose
+    /// fee would overrun the payment is cut to what `amount` nets.
+    pub fn fit_reward(&self, reward: u64, amount: u64, fee_mode: TransferFeeMode) -> Result<u64> {
+        let reward = reward.min(amount);
+        if fee_mode == TransferFeeMode::SubscriberPays || self.gross_up(reward)? <= amount {
+            return Ok(reward);
+        }
+        let net = self.net_of(amount)?;
+        Ok(if self.gross_up(net)? <= amount {
+            net
+        } else {
+            0
+        })
+    }
+
+    /// What `execute_token_transfer` sends for a payment of `amount`: the
+    /// cranker's grossed-up `crank_reward`, then the rest to the treasury (or
+    /// one amount per `revenue_split` recipient). Payees' shares are grossed
+    /// up individually when the subscriber bears the fee.
+    pub fn transfer_legs(
+        &self,
+        amount: u64,
+        crank_reward: u64,
+        revenue_split: Option<&RevenueSplit>,
+        fee_mode: TransferFeeMode,
+    ) -> Result<(u64, Vec<u64>)> {
+        let cranker_gross = self.gross_up(crank_reward)?;
+        let net = match fee_mode {
+            TransferFeeMode::MerchantAbsorbs => amount.checked_sub(cranker_gross),
+            TransferFeeMode::SubscriberPays => amount.checked_sub(crank_reward),
+        }
+        .ok_or(SolBillError::Overflow)?;
+        let shares = match revenue_split {
+            Some(split) => split.allocate(net)?,
+            None => vec![net],
+        };
+        let payee_amounts = match fee_mode {
+            TransferFeeMode::MerchantAbsorbs => shares,
+            TransferFeeMode::SubscriberPays => shares
+                .into_iter()
+                .map(|share| self.gross_up(share))
+                .collect::<Result<_>>()?,
+        };
+        Ok((cranker_gross, payee_amounts))
+    }
+
+    /// What `execute_token_transfer` debits from the subscriber for the same
+    /// arguments: each transfer is grossed up and rounded on its own, so the
+    /// fee the subscriber bears is summed per leg. Spending caps are held to
+    /// this.
+    pub fn debit_for(
+        &self,
+        amount: u64,
+        crank_reward: u64,
+        revenue_split: Option<&RevenueSplit>,
+        fee_mode: TransferFeeMode,
+    ) -> Result<u64> {
+        let (cranker_gross, payee_amounts) =
+            self.transfer_legs(amount, crank_reward, revenue_split, fee_mode)?;
+        payee_amounts
+            .into_iter()
+            .try_fold(cranker_gross, |total, gross| total.checked_add(gross))
+            .ok_or_else(|| error!(SolBillError::Overflow))
+    }
+
+    /// Upper bound on what one charge of `amount` debits from the subscriber
+    /// across `transfers` transfers, under either the current or the scheduled
+    /// fee. Used to size the delegated allowance.
+    pub fn max_charge(
+        &self,
+        amount: u64,
+        transfers: u64,
+        fee_mode: TransferFeeMode,
+    ) -> Result<u64> {
+        let Some(config) = self
+            .config
+            .as_ref()
+            .filter(|_| fee_mode == TransferFeeMode::SubscriberPays)
+        else {
+            return Ok(amount);
+        };
+        let mut max_fee = 0u64;
+        for fee in [&config.older_transfer_fee, &config.newer_transfer_fee] {
+            let gross = fee
+                .calculate_pre_fee_amount(amount)
+                .ok_or(SolBillError::Overflow)?;
+            max_fee = max_fee.max(gross - amount);
+        }
+        // Each transfer is charged (and rounded) separately
+        max_fee
+            .checked_mul(transfers)
+            .and_then(|fees| amount.checked_add(fees))
+            .ok_or_else(|| error!(SolBillError::Overflow))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: Option<&[&[&[u8]]]>,
+    hook_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    // Token-2022 resolves the hook's extra accounts from the ones supplied
+    if !hook_accounts.is_empty() {
+        return spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            from.to_account_info(),
+            mint.to_account_info(),
+            to.clone(),
+            authority.clone(),
+            hook_accounts,
+            amount,
+            mint.decimals,
+            signer_seeds.unwrap_or_default(),
+        )
+        .map_err(Into::into);
     }
 
-    // 2. Transfer remainder to Treasury
     let cpi_accounts = TransferChecked {
         from: from.to_account_info(),
-        to: to_treasury.to_account_info(),
+        to: to.clone(),
         authority: authority.clone(),
         mint: mint.to_account_info(),
     };
@@ -57,11 +316,287 @@ pub fn execute_token_transfer<'info>(
 
     if let Some(seeds) = signer_seeds {
         let cpi_ctx = CpiContext::new_with_signer(cpi_p, cpi_accounts, seeds);
-        transfer_checked(cpi_ctx, treasury_amount, mint.decimals)?;
+        transfer_checked(cpi_ctx, amount, mint.decimals)
     } else {
         let cpi_ctx = CpiContext::new(cpi_p, cpi_accounts);
-        transfer_checked(cpi_ctx, treasury_amount, mint.decimals)?;
+        transfer_checked(cpi_ctx, amount, mint.decimals)
     }
+}
+
+/// Pay an affiliate's referral reward out of a subscriber's charge.
+#[allow(clippy::too_many_arguments)]
+pub fn pay_affiliate<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: Option<&[&[&[u8]]]>,
+    hook_accounts: &[AccountInfo<'info>],
+) -> Result<u64> {
+    if amount == 0 {
+        return Ok(0);
+    }
+    let withheld = TransferFeeSchedule::load(&mint.to_account_info())?.fee_on(amount)?;
+    transfer_tokens(
+        token_program,
+        from,
+        to,
+        mint,
+        authority,
+        amount,
+        signer_seeds,
+        hook_accounts,
+    )?;
+    Ok(withheld)
+}
+
+/// Check that the supplied `RevenueSplit` (if any) is the one configured for
+/// the plan, falling back to the service-wide split, for a payment in `mint`.
+pub fn validate_revenue_split(
+    service: &ServiceAccount,
+    plan: &PlanAccount,
+    mint: &Pubkey,
+    revenue_split: Option<&Account<RevenueSplit>>,
+) -> Result<()> {
+    require!(
+        expected_revenue_split(service, plan, mint) == revenue_split.map(|s| s.key()),
+        SolBillError::RevenueSplitMismatch
+    );
+    Ok(())
+}
+
+/// The `RevenueSplit` a payment in `mint` on `plan` is paid out through, if any.
+pub fn expected_revenue_split(
+    service: &ServiceAccount,
+    plan: &PlanAccount,
+    mint: &Pubkey,
+) -> Option<Pubkey> {
+    // Splits pay out in the plan's current mint; subscriptions left in an
+    // earlier mint pay their own treasury. A service-wide split pays out in
+    // the service's mint only
+    let plan_mint = plan.mint_for(service);
+    let service_split = service
+        .revenue_split
+        .filter(|_| plan_mint == service.accepted_mint);
+    plan.revenue_split
+        .or(service_split)
+        .filter(|_| plan_mint == *mint)
+}
+
+/// Approve the subscription PDA as delegate on the subscriber's token account
+/// for its next charge (`SubscriptionAccount::delegation_amount`), grossed up
+/// for any transfer fee on each transfer a collection makes. `revenue_split`
+/// must be the split configured for `plan`. Returns the approved allowance.
+#[allow(clippy::too_many_arguments)]
+pub fn approve_delegation<'info>(
+    service: &ServiceAccount,
+    plan: &PlanAccount,
+    subscription: &Account<'info, SubscriptionAccount>,
+    revenue_split: Option<&Account<'info, RevenueSplit>>,
+    subscriber: &Signer<'info>,
+    subscriber_token_account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<u64> {
+    validate_revenue_split(service, plan, &subscription.mint, revenue_split)?;
+    let allowance = TransferFeeSchedule::load(&mint.to_account_info())?.max_charge(
+        subscription.delegation_amount()?,
+        charge_transfers(revenue_split.map(|split| &**split)),
+        subscription.transfer_fee_mode,
+    )?;
+    approve(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Approve {
+                to: subscriber_token_account.to_account_info(),
+                delegate: subscription.to_account_info(),
+                authority: subscriber.to_account_info(),
+            },
+        ),
+        allowance,
+    )?;
+    Ok(allowance)
+}
+
+/// Pay `amount` lamports from the pool to `recipient` if the pool can cover it.
+/// Returns whether the reward was paid, so callers can fall back gracefully.
+pub fn pay_from_reward_pool(
+    pool: &mut Account<RewardPool>,
+    recipient: &AccountInfo,
+    amount: u64,
+) -> Result<bool> {
+    let pool_info = pool.to_account_info();
+    if amount == 0 || RewardPool::available_lamports(&pool_info)? < amount {
+        return Ok(false);
+    }
+
+    **pool_info.try_borrow_mut_lamports()? -= amount;
+    **recipient.try_borrow_mut_lamports()? += amount;
+    pool.total_paid = pool
+        .total_paid
+        .checked_add(amount)
+        .ok_or(SolBillError::Overflow)?;
+    Ok(true)
+}
+
+/// Transfers one collection makes from the subscriber: the cranker's reward,
+/// then the treasury or each split payee. Sizes the delegated allowance.
+pub fn charge_transfers(revenue_split: Option<&RevenueSplit>) -> u64 {
+    1 + revenue_split.map_or(1, |split| split.recipients.len() as u64)
+}
+
+/// Whether `token_account` can currently fund a charge of `amount` pulled by
+/// `delegate` (unfrozen, and balance and approved allowance both suffice).
+pub fn can_collect(token_account: &TokenAccount, delegate: &Pubkey, amount: u64) -> bool {
+    !token_account.is_frozen()
+        && token_account.delegate.contains(delegate)
+        && token_account.delegated_amount >= amount
+        && token_account.amount >= amount
+}
+
+/// Whether `account` is an open, unfrozen token account of `mint` under
+/// `token_program`, i.e. a transfer to it would go through.
+pub fn can_receive(account: &AccountInfo, mint: &Pubkey, token_program: &Pubkey) -> bool {
+    if account.owner != token_program {
+        return false;
+    }
+    let Ok(data) = account.try_borrow_data() else {
+        return false;
+    };
+    TokenAccount::try_deserialize(&mut &data[..])
+        .is_ok_and(|token_account| token_account.mint == *mint && !token_account.is_frozen())
+}
+
+/// Close the seat and add-on PDAs of a subscription that is itself being
+/// closed, refunding their rent to the subscriber. `accounts` holds seat
+/// assignments followed by every attached `SubscriptionItem`. With
+/// `all_seats`, every assigned seat must be among them; otherwise any seats
+/// left behind are stale (their generation no longer matches) and can be
+/// removed later.
+pub fn close_subscription_dependents<'info>(
+    subscription: &Account<'info, SubscriptionAccount>,
+    accounts: &'info [AccountInfo<'info>],
+    subscriber: &AccountInfo<'info>,
+    all_seats: bool,
+) -> Result<()> {
+    let item_count = subscription.items.len();
+    require!(accounts.len() >= item_count, SolBillError::ItemsNotReleased);
+    let (seats, items) = accounts.split_at(accounts.len() - item_count);
+    require!(
+        !all_seats || seats.len() == subscription.assigned_seats as usize,
+        SolBillError::SeatsNotReleased
+    );
+
+    for info in seats {
+        let seat = Account::<SeatAssignment>::try_from(info)?;
+        require_keys_eq!(
+            seat.subscription,
+            subscription.key(),
+            SolBillError::SeatsNotReleased
+        );
+        seat.close(subscriber.clone())?;
+    }
+    for info in items {
+        let item = Account::<SubscriptionItem>::try_from(info)?;
+        require_keys_eq!(
+            item.subscription,
+            subscription.key(),
+            SolBillError::ItemsNotReleased
+        );
+        item.close(subscriber.clone())?;
+    }
+    Ok(())
+}
+
+/// Lamports a native SOL escrow can spend while staying rent exempt.
+pub fn escrow_available_lamports(escrow: &AccountInfo) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(0);
+    Ok(escrow.lamports().saturating_sub(rent_exempt))
+}
+
+/// Move `amount` lamports out of a subscription's escrow PDA.
+pub fn transfer_from_escrow<'info>(
+    system_program: &Program<'info, System>,
+    escrow: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: escrow.clone(),
+                to: to.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )
+}
+
+/// Read and vet the current price for a quote-priced subscription.
+/// Returns `None` for fixed-price subscriptions.
+pub fn read_quote_price(
+    pricing: Option<&QuotePricing>,
+    price_feed: Option<&AccountInfo>,
+    now: i64,
+) -> Result<Option<PriceData>> {
+    let Some(pricing) = pricing else {
+        return Ok(None);
+    };
+    let feed = price_feed.ok_or(SolBillError::InvalidPriceFeed)?;
+    require_keys_eq!(
+        feed.key(),
+        pricing.price_feed,
+        SolBillError::InvalidPriceFeed
+    );
+    let price = pricing.read_price(feed)?;
+    pricing.check(&price, now)?;
+    Ok(Some(price))
+}
+
+/// Require that the instruction just before the current one is an ed25519
+/// precompile check of `message` signed by `signer`, with every offset
+/// pointing into the precompile instruction itself.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let ix = get_instruction_relative(-1, instructions_sysvar)
+        .map_err(|_| SolBillError::InvalidOfferSignature)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        SolBillError::InvalidOfferSignature
+    );
+
+    // [num_signatures, padding, Ed25519SignatureOffsets (7 x u16), ...]
+    let data = &ix.data;
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        SolBillError::InvalidOfferSignature
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        SolBillError::InvalidOfferSignature
+    );
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
 
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    require!(
+        public_key == Some(signer.as_ref()) && signed_message == Some(message),
+        SolBillError::InvalidOfferSignature
+    );
     Ok(())
 }
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::{RewardPool, ServiceAccount};
+
+#[derive(Accounts)]
+pub struct WithdrawRewardPool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"service", authority.key().as_ref()],
+        bump = service.bump,
+        has_one = authority @ SolBillError::UnauthorizedAuthority,
+    )]
+    pub service: Account<'info, ServiceAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_pool", service.key().as_ref()],
+        bump = reward_pool.bump,
+        has_one = service,
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+}
+
+pub fn handler(ctx: Context<WithdrawRewardPool>, amount: u64) -> Result<()> {
+    require!(amount > 0, SolBillError::InvalidAmount);
+
+    // The pool must stay rent-exempt, so only the excess can be withdrawn
+    let pool_info = ctx.accounts.reward_pool.to_account_info();
+    require!(
+        RewardPool::available_lamports(&pool_info)? >= amount,
+        SolBillError::InsufficientPoolBalance
+    );
+
+    **pool_info.try_borrow_mut_lamports()? -= amount;
+    **ctx.accounts.authority.try_borrow_mut_lamports()? += amount;
+
+    msg!("Withdrew {} lamports from reward pool", amount);
+    Ok(())
+}
@@ -4,13 +4,19 @@
 use anchor_lang::prelude::*;
 
 pub mod errors;
+pub mod events;
 pub mod instructions;
+pub mod oracle;
 pub mod state;
 
 #[cfg(test)]
 mod tests;
 
 use instructions::*;
+use state::{
+    AmountRange, CouponDuration, Discount, LateFee, LoyaltyTier, PercentageReward, PricePhase,
+    PrivateOffer, QuotePricing, RewardEscalation, SpendingCaps, SplitRecipient, TransferFeeMode,
+};
 
 declare_id!("AK2xA7SHMKPqvQEirLUNf4gRQjzpQZT3q6v3d62kLyzx");
 
@@ -24,6 +30,7 @@ pub mod solbill {
     }
 
     /// Merchant: Create a subscription plan under the service.
+    #[allow(clippy::too_many_arguments)]
     pub fn create_plan(
         ctx: Context<CreatePlan>,
         name: String,
@@ -32,6 +39,8 @@ pub mod solbill {
         interval: i64,
         grace_period: i64,
         max_billing_cycles: u64,
+        percentage_reward: Option<PercentageReward>,
+        native_sol: bool,
     ) -> Result<()> {
         instructions::create_plan::handler(
             ctx,
@@ -41,6 +50,8 @@ pub mod solbill {
             interval,
             grace_period,
             max_billing_cycles,
+            percentage_reward,
+            native_sol,
         )
     }
 
@@ -63,28 +74,310 @@ pub mod solbill {
         )
     }
 
-    /// Subscriber: Subscribe to a plan.
-    pub fn create_subscription(ctx: Context<CreateSubscription>) -> Result<()> {
-        instructions::create_subscription::handler(ctx)
+    /// Merchant: Set a plan's percentage crank reward and overdue escalation
+    /// (`None` returns to the fixed `crank_reward` / disables escalation).
+    pub fn set_reward_policy(
+        ctx: Context<SetRewardPolicy>,
+        percentage_reward: Option<PercentageReward>,
+        reward_escalation: Option<RewardEscalation>,
+    ) -> Result<()> {
+        instructions::set_reward_policy::handler(ctx, percentage_reward, reward_escalation)
+    }
+
+    /// Merchant: Reserve collection to the merchant's collector for `exclusive_window`
+    /// seconds after each due date (0 = public immediately).
+    pub fn set_exclusive_window(
+        ctx: Context<SetExclusiveWindow>,
+        exclusive_window: i64,
+    ) -> Result<()> {
+        instructions::set_exclusive_window::handler(ctx, exclusive_window)
+    }
+
+    /// Merchant: Choose who bears the mint's transfer fee on this plan's payments.
+    pub fn set_transfer_fee_mode(
+        ctx: Context<SetTransferFeeMode>,
+        transfer_fee_mode: TransferFeeMode,
+    ) -> Result<()> {
+        instructions::set_transfer_fee_mode::handler(ctx, transfer_fee_mode)
+    }
+
+    /// Merchant: Set the one-time activation fee charged with the first period (0 = none).
+    pub fn set_setup_fee(ctx: Context<SetSetupFee>, setup_fee: u64) -> Result<()> {
+        instructions::set_setup_fee::handler(ctx, setup_fee)
+    }
+
+    /// Merchant: Set (or clear) the fee added when collecting a past-due subscription.
+    pub fn set_late_fee(ctx: Context<SetLateFee>, late_fee: Option<LateFee>) -> Result<()> {
+        instructions::set_late_fee::handler(ctx, late_fee)
+    }
+
+    /// Merchant: Designate a collector key for plan exclusivity windows.
+    pub fn set_collector(ctx: Context<SetCollector>, collector: Option<Pubkey>) -> Result<()> {
+        instructions::set_collector::handler(ctx, collector)
+    }
+
+    /// Merchant: Price a plan in a different mint, paid to its own treasury.
+    pub fn set_plan_currency(ctx: Context<SetPlanCurrency>) -> Result<()> {
+        instructions::set_plan_currency::handler(ctx)
+    }
+
+    /// Merchant: Price a plan in a quote currency, converted through a price account
+    /// at each charge (or pass `None` to return to fixed token pricing).
+    pub fn set_quote_pricing(
+        ctx: Context<SetQuotePricing>,
+        pricing: Option<QuotePricing>,
+    ) -> Result<()> {
+        instructions::set_quote_pricing::handler(ctx, pricing)
+    }
+
+    /// Merchant: Set (or clear) the wallet receiving native SOL payments.
+    pub fn set_sol_treasury(
+        ctx: Context<SetSolTreasury>,
+        sol_treasury: Option<Pubkey>,
+    ) -> Result<()> {
+        instructions::set_sol_treasury::handler(ctx, sol_treasury)
+    }
+
+    /// Merchant: Share plan (or service-wide) revenue across multiple payees.
+    pub fn create_revenue_split<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateRevenueSplit<'info>>,
+        recipients: Vec<SplitRecipient>,
+        primary_index: u8,
+    ) -> Result<()> {
+        instructions::create_revenue_split::handler(ctx, recipients, primary_index)
+    }
+
+    /// Merchant: Remove a revenue split (payments go back to the treasury).
+    pub fn close_revenue_split(ctx: Context<CloseRevenueSplit>) -> Result<()> {
+        instructions::close_revenue_split::handler(ctx)
+    }
+
+    /// Merchant: Create a lamport-funded pool that pays crankers instead of the payment mint.
+    pub fn create_reward_pool(
+        ctx: Context<CreateRewardPool>,
+        lamports_per_crank: u64,
+        maintenance_reward: u64,
+    ) -> Result<()> {
+        instructions::create_reward_pool::handler(ctx, lamports_per_crank, maintenance_reward)
+    }
+
+    /// Anyone: Top up a service's crank reward pool.
+    pub fn deposit_reward_pool(ctx: Context<DepositRewardPool>, amount: u64) -> Result<()> {
+        instructions::deposit_reward_pool::handler(ctx, amount)
+    }
+
+    /// Merchant: Withdraw lamports from the crank reward pool (rent stays in the pool).
+    pub fn withdraw_reward_pool(ctx: Context<WithdrawRewardPool>, amount: u64) -> Result<()> {
+        instructions::withdraw_reward_pool::handler(ctx, amount)
+    }
+
+    /// Merchant: Change the lamports paid per collection and per maintenance crank.
+    pub fn update_reward_pool(
+        ctx: Context<UpdateRewardPool>,
+        lamports_per_crank: u64,
+        maintenance_reward: u64,
+    ) -> Result<()> {
+        instructions::update_reward_pool::handler(ctx, lamports_per_crank, maintenance_reward)
+    }
+
+    /// Merchant: Create a promotion code discounting subscriptions that redeem it.
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        code: [u8; 32],
+        discount: Discount,
+        duration: CouponDuration,
+        max_redemptions: u32,
+        expires_at: i64,
+        eligible_plans: Vec<Pubkey>,
+    ) -> Result<()> {
+        instructions::create_coupon::handler(
+            ctx,
+            code,
+            discount,
+            duration,
+            max_redemptions,
+            expires_at,
+            eligible_plans,
+        )
+    }
+
+    /// Merchant: Register an affiliate paid `reward_bps` of the first `reward_cycles`
+    /// collections of every subscription it refers.
+    pub fn register_affiliate(
+        ctx: Context<RegisterAffiliate>,
+        reward_bps: u16,
+        reward_cycles: u32,
+    ) -> Result<()> {
+        instructions::register_affiliate::handler(ctx, reward_bps, reward_cycles)
+    }
+
+    /// Merchant: Set a plan's tenure discounts (applies to new subscriptions and plan changes).
+    pub fn set_loyalty_tiers(ctx: Context<SetLoyaltyTiers>, tiers: Vec<LoyaltyTier>) -> Result<()> {
+        instructions::set_loyalty_tiers::handler(ctx, tiers)
+    }
+
+    /// Merchant: Set a plan's ramped pricing schedule (applies to new subscriptions
+    /// and plan changes; an empty list returns to flat pricing).
+    pub fn set_price_phases(ctx: Context<SetPricePhases>, phases: Vec<PricePhase>) -> Result<()> {
+        instructions::set_price_phases::handler(ctx, phases)
+    }
+
+    /// Merchant: Let subscribers choose their amount within a range (or pass `None`
+    /// to return to fixed pricing).
+    pub fn set_pay_what_you_want(
+        ctx: Context<SetPayWhatYouWant>,
+        range: Option<AmountRange>,
+    ) -> Result<()> {
+        instructions::set_pay_what_you_want::handler(ctx, range)
+    }
+
+    /// Merchant: Set the fewest and most seats a subscription may hold (0 = no maximum).
+    pub fn set_seat_limits(
+        ctx: Context<SetSeatLimits>,
+        min_quantity: u32,
+        max_quantity: u32,
+    ) -> Result<()> {
+        instructions::set_seat_limits::handler(ctx, min_quantity, max_quantity)
+    }
+
+    /// Merchant: Queue a one-off charge (e.g. an overage) for the next collection.
+    /// The subscriber's approved allowance must already cover it.
+    pub fn add_one_off_charge(ctx: Context<AddOneOffCharge>, amount: u64) -> Result<()> {
+        instructions::add_one_off_charge::handler(ctx, amount)
+    }
+
+    /// Subscriber: Subscribe to a plan. `max_token_amount` caps any single charge
+    /// (0 = no cap; required for quote-priced plans). A private `offer` signed by the
+    /// service authority replaces the plan's amount and interval, and can be accepted
+    /// only once (its nonce's `OfferRedemption` is created here). Pay-what-you-want
+    /// plans take a `chosen_amount` (defaults to the plan's floor).
+    pub fn create_subscription<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateSubscription<'info>>,
+        max_token_amount: u64,
+        offer: Option<PrivateOffer>,
+        chosen_amount: Option<u64>,
+    ) -> Result<()> {
+        instructions::create_subscription::handler(ctx, max_token_amount, offer, chosen_amount)
+    }
+
+    /// Subscriber: Subscribe to a native SOL plan, pre-funding its lamport escrow.
+    pub fn create_native_subscription(
+        ctx: Context<CreateNativeSubscription>,
+        prefund: u64,
+    ) -> Result<()> {
+        instructions::create_native_subscription::handler(ctx, prefund)
+    }
+
+    /// Anyone: Top up a native SOL subscription's escrow.
+    pub fn fund_escrow(ctx: Context<FundEscrow>, amount: u64) -> Result<()> {
+        instructions::fund_escrow::handler(ctx, amount)
+    }
+
+    /// Subscriber: Cancel a native SOL subscription and refund its escrow.
+    /// Assigned seats and attached add-ons are passed as remaining accounts and closed with it.
+    pub fn cancel_native_subscription<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelNativeSubscription<'info>>,
+    ) -> Result<()> {
+        instructions::cancel_native_subscription::handler(ctx)
+    }
+
+    /// Subscriber: Cap what may be charged per charge, per period and in total.
+    pub fn set_spending_caps(ctx: Context<SetSpendingCaps>, caps: SpendingCaps) -> Result<()> {
+        instructions::set_spending_caps::handler(ctx, caps)
+    }
+
+    /// Subscriber: Re-approve the delegation after a pricing phase raised the price.
+    pub fn reapprove_delegation(ctx: Context<ReapproveDelegation>) -> Result<()> {
+        instructions::reapprove_delegation::handler(ctx)
+    }
+
+    /// Subscriber: Change the amount paid on a pay-what-you-want plan.
+    pub fn update_chosen_amount(ctx: Context<UpdateChosenAmount>, amount: u64) -> Result<()> {
+        instructions::update_chosen_amount::handler(ctx, amount)
+    }
+
+    /// Subscriber: Change the number of seats, with a prorated charge or credit
+    /// for the rest of the current period.
+    pub fn update_quantity(ctx: Context<UpdateQuantity>, quantity: u32) -> Result<()> {
+        instructions::update_quantity::handler(ctx, quantity)
+    }
+
+    /// Subscriber: Assign one of the subscription's seats to a member wallet.
+    pub fn add_member(ctx: Context<AddMember>) -> Result<()> {
+        instructions::add_member::handler(ctx)
+    }
+
+    /// Subscriber: Free a member's seat.
+    pub fn remove_member(ctx: Context<RemoveMember>) -> Result<()> {
+        instructions::remove_member::handler(ctx)
+    }
+
+    /// Subscriber: Attach an add-on plan, billed with each recurring charge.
+    pub fn attach_item(ctx: Context<AttachItem>) -> Result<()> {
+        instructions::attach_item::handler(ctx)
+    }
+
+    /// Subscriber: Detach an add-on plan from the next charge on.
+    pub fn detach_item(ctx: Context<DetachItem>) -> Result<()> {
+        instructions::detach_item::handler(ctx)
     }
 
     /// Subscriber: Cancel an active subscription (instant, revokes token delegation).
-    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+    /// Assigned seats and attached add-ons are passed as remaining accounts and closed with it.
+    pub fn cancel_subscription<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelSubscription<'info>>,
+    ) -> Result<()> {
         instructions::cancel_subscription::handler(ctx)
     }
 
-    /// Subscriber: Switch to a different plan.
-    pub fn change_plan(ctx: Context<ChangePlan>) -> Result<()> {
-        instructions::change_plan::handler(ctx)
+    /// Subscriber: Switch to a different plan. `max_token_amount` caps any single charge
+    /// (required for quote-priced plans); pay-what-you-want plans take a `chosen_amount`
+    /// (defaults to the plan's floor).
+    pub fn change_plan(
+        ctx: Context<ChangePlan>,
+        max_token_amount: u64,
+        chosen_amount: Option<u64>,
+    ) -> Result<()> {
+        instructions::change_plan::handler(ctx, max_token_amount, chosen_amount)
     }
 
     /// Merchant/Worker: Collect a due payment from a subscriber.
-    pub fn collect_payment(ctx: Context<CollectPayment>) -> Result<()> {
+    pub fn collect_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CollectPayment<'info>>,
+    ) -> Result<()> {
         instructions::collect_payment::handler(ctx)
     }
 
+    /// Merchant/Worker: Collect a due lamport payment from a subscription escrow.
+    pub fn collect_native_payment(ctx: Context<CollectNativePayment>) -> Result<()> {
+        instructions::collect_native_payment::handler(ctx)
+    }
+
+    /// Merchant/Worker: Collect every due payment in a batch of subscriptions,
+    /// skipping the ones that are not collectable.
+    pub fn collect_payments_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CollectPaymentsBatch<'info>>,
+        extra_accounts: Vec<u8>,
+    ) -> Result<()> {
+        instructions::collect_payments_batch::handler(ctx, extra_accounts)
+    }
+
+    /// Anyone: Fail unless a wallet (the subscriber or a seat member) currently
+    /// has access through a subscription.
+    pub fn check_access(ctx: Context<CheckAccess>) -> Result<()> {
+        instructions::check_access::handler(ctx)
+    }
+
+    /// Anyone: Flag a due subscription whose payment cannot be collected.
+    pub fn mark_past_due(ctx: Context<MarkPastDue>) -> Result<()> {
+        instructions::mark_past_due::handler(ctx)
+    }
+
     /// Anyone: Expire a past-due subscription after grace period.
-    pub fn expire_subscription(ctx: Context<ExpireSubscription>) -> Result<()> {
+    /// Attached add-ons, and any assigned seats, are passed as remaining accounts and closed with it.
+    pub fn expire_subscription<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExpireSubscription<'info>>,
+    ) -> Result<()> {
         instructions::expire_subscription::handler(ctx)
     }
 }
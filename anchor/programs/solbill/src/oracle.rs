@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+
+/// Pyth pull-oracle receiver program that owns `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// A price observation: `price * 10^exponent` quote units per whole token,
+/// with a ± `conf` confidence interval on the same scale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceData {
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+/// Decodes a price account into a `PriceData`. Implement this and add a
+/// `PriceSource` variant to support another oracle format.
+pub trait PriceReader {
+    fn read_price(account: &AccountInfo) -> Result<PriceData>;
+}
+
+/// Reads Pyth `PriceUpdateV2` accounts posted by the pull-oracle receiver.
+pub struct PythPriceReader;
+
+impl PythPriceReader {
+    const DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+    /// `VerificationLevel::Full` (only fully verified updates are accepted).
+    const FULLY_VERIFIED: u8 = 1;
+}
+
+impl PriceReader for PythPriceReader {
+    fn read_price(account: &AccountInfo) -> Result<PriceData> {
+        require_keys_eq!(
+            *account.owner,
+            PYTH_RECEIVER_ID,
+            SolBillError::InvalidPriceFeed
+        );
+        let data = account.try_borrow_data()?;
+
+        // discriminator | write_authority | verification_level | feed_id | price ...
+        require!(
+            data.len() >= 8 + 32 + 1 + 32 + 8 + 8 + 4 + 8
+                && data[..8] == Self::DISCRIMINATOR
+                && data[40] == Self::FULLY_VERIFIED,
+            SolBillError::InvalidPriceFeed
+        );
+        let message = &data[41 + 32..];
+        let read = |offset: usize, len: usize| &message[offset..offset + len];
+
+        Ok(PriceData {
+            price: i64::from_le_bytes(read(0, 8).try_into().unwrap()),
+            conf: u64::from_le_bytes(read(8, 8).try_into().unwrap()),
+            exponent: i32::from_le_bytes(read(16, 4).try_into().unwrap()),
+            publish_time: i64::from_le_bytes(read(20, 8).try_into().unwrap()),
+        })
+    }
+}
+
+/// Oracle format a quote-priced plan's price account is read with. Add a
+/// variant (and its `PriceReader`) to support another oracle.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub enum PriceSource {
+    /// Pyth pull-oracle `PriceUpdateV2` accounts.
+    #[default]
+    Pyth,
+}
+
+impl PriceSource {
+    pub fn read_price(self, account: &AccountInfo) -> Result<PriceData> {
+        match self {
+            PriceSource::Pyth => PythPriceReader::read_price(account),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::BPS_DENOMINATOR;
+
+/// A referrer registered with a service, paid a share of the subscriptions it brings in.
+#[account]
+#[derive(InitSpace)]
+pub struct Affiliate {
+    /// Parent `ServiceAccount` pubkey.
+    pub service: Pubkey,
+    /// The affiliate's wallet (used in PDA seeds).
+    pub wallet: Pubkey,
+    /// Token account receiving the affiliate's rewards.
+    pub payout_token_account: Pubkey,
+    /// Share of each rewarded collection paid to the affiliate, in basis points.
+    pub reward_bps: u16,
+    /// Number of collections per referred subscription that pay a reward.
+    pub reward_cycles: u32,
+    /// Subscriptions referred so far.
+    pub referrals: u32,
+    /// Rewards paid so far.
+    pub total_rewards: u64,
+    /// Revenue collected on rewarded collections.
+    pub total_referred_revenue: u64,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+impl Affiliate {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.reward_bps > 0 && (self.reward_bps as u64) <= BPS_DENOMINATOR,
+            SolBillError::InvalidAffiliate
+        );
+        require!(self.reward_cycles > 0, SolBillError::InvalidAffiliate);
+        Ok(())
+    }
+
+    /// Record a new referral and the terms it locks into the subscription.
+    pub fn refer(&mut self, affiliate_key: Pubkey) -> Result<Referral> {
+        self.referrals = self
+            .referrals
+            .checked_add(1)
+            .ok_or(SolBillError::Overflow)?;
+        Ok(Referral {
+            affiliate: affiliate_key,
+            payout_token_account: self.payout_token_account,
+            reward_bps: self.reward_bps,
+            remaining_payouts: self.reward_cycles,
+        })
+    }
+
+    /// Add a paid reward of `reward` on a collection of `revenue` to the totals.
+    pub fn record_reward(&mut self, reward: u64, revenue: u64) -> Result<()> {
+        self.total_rewards = self
+            .total_rewards
+            .checked_add(reward)
+            .ok_or(SolBillError::Overflow)?;
+        self.total_referred_revenue = self
+            .total_referred_revenue
+            .checked_add(revenue)
+            .ok_or(SolBillError::Overflow)?;
+        Ok(())
+    }
+}
+
+/// Referral terms locked into a subscription.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Referral {
+    /// The referring `Affiliate`.
+    pub affiliate: Pubkey,
+    /// Token account receiving the rewards.
+    pub payout_token_account: Pubkey,
+    /// Share of each rewarded collection, in basis points.
+    pub reward_bps: u16,
+    /// Collections left that pay a reward.
+    pub remaining_payouts: u32,
+}
+
+impl Referral {
+    /// Reward owed on a collection of `amount`.
+    pub fn reward_for(&self, amount: u64) -> Result<u64> {
+        let scaled = (amount as u128)
+            .checked_mul(self.reward_bps as u128)
+            .ok_or(SolBillError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        u64::try_from(scaled).map_err(|_| error!(SolBillError::Overflow))
+    }
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+use crate::state::BPS_DENOMINATOR;
+
+/// Maximum number of plans a coupon can be restricted to.
+pub const MAX_COUPON_PLANS: usize = 8;
+
+/// Maximum number of distinct coupons one subscription can redeem.
+pub const MAX_REDEEMED_COUPONS: usize = 8;
+
+/// Reduction a coupon applies to each discounted charge.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum Discount {
+    /// Share of the charge taken off, in basis points.
+    PercentOff(u16),
+    /// Fixed amount taken off, in the same units as the plan's `amount`.
+    AmountOff(u64),
+}
+
+impl Discount {
+    /// `amount` after the discount, never below zero.
+    pub fn apply(&self, amount: u64) -> Result<u64> {
+        let off = match *self {
+            Discount::PercentOff(bps) => {
+                let scaled = (amount as u128)
+                    .checked_mul(bps as u128)
+                    .ok_or(SolBillError::Overflow)?
+                    / BPS_DENOMINATOR as u128;
+                u64::try_from(scaled).map_err(|_| SolBillError::Overflow)?
+            }
+            Discount::AmountOff(off) => off,
+        };
+        Ok(amount.saturating_sub(off))
+    }
+}
+
+/// How many charges a redeemed coupon discounts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CouponDuration {
+    /// Only the next charge.
+    Once,
+    /// The next N charges.
+    Cycles(u32),
+    /// Every charge for the life of the subscription.
+    Forever,
+}
+
+/// A discount redeemed onto a subscription.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct AppliedDiscount {
+    /// The redeemed `Coupon`.
+    pub coupon: Pubkey,
+    pub discount: Discount,
+    /// Charges left to discount (`None` = forever).
+    pub remaining_cycles: Option<u32>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Coupon {
+    /// Parent `ServiceAccount` pubkey.
+    pub service: Pubkey,
+    /// Promotion code, fixed 32 bytes (UTF-8, zero-padded; used in PDA seeds).
+    pub code: [u8; 32],
+    pub discount: Discount,
+    pub duration: CouponDuration,
+    /// Limit on total redemptions (0 = unlimited).
+    pub max_redemptions: u32,
+    /// Number of times the coupon has been redeemed.
+    pub redemptions: u32,
+    /// Unix timestamp after which the coupon can no longer be redeemed (0 = never).
+    pub expires_at: i64,
+    /// Plans the coupon can be redeemed on (empty = every plan of the service).
+    #[max_len(MAX_COUPON_PLANS)]
+    pub eligible_plans: Vec<Pubkey>,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+impl Coupon {
+    pub fn validate(&self) -> Result<()> {
+        match self.discount {
+            Discount::PercentOff(bps) => require!(
+                bps > 0 && (bps as u64) <= BPS_DENOMINATOR,
+                SolBillError::InvalidCoupon
+            ),
+            Discount::AmountOff(off) => require!(off > 0, SolBillError::InvalidCoupon),
+        }
+        if let CouponDuration::Cycles(cycles) = self.duration {
+            require!(cycles > 0, SolBillError::InvalidCoupon);
+        }
+        require!(
+            self.eligible_plans.len() <= MAX_COUPON_PLANS,
+            SolBillError::InvalidCoupon
+        );
+        Ok(())
+    }
+
+    /// Redeem the coupon at `coupon_key` onto a subscription to `plan` at `now`.
+    pub fn redeem(
+        &mut self,
+        coupon_key: Pubkey,
+        plan: &Pubkey,
+        now: i64,
+    ) -> Result<AppliedDiscount> {
+        require!(
+            self.expires_at == 0 || now < self.expires_at,
+            SolBillError::CouponExpired
+        );
+        require!(
+            self.max_redemptions == 0 || self.redemptions < self.max_redemptions,
+            SolBillError::CouponRedemptionLimitReached
+        );
+        require!(
+            self.eligible_plans.is_empty() || self.eligible_plans.contains(plan),
+            SolBillError::CouponNotEligible
+        );
+        self.redemptions = self
+            .redemptions
+            .checked_add(1)
+            .ok_or(SolBillError::Overflow)?;
+
+        Ok(AppliedDiscount {
+            coupon: coupon_key,
+            discount: self.discount,
+            remaining_cycles: match self.duration {
+                CouponDuration::Once => Some(1),
+                CouponDuration::Cycles(cycles) => Some(cycles),
+                CouponDuration::Forever => None,
+            },
+        })
+    }
+}
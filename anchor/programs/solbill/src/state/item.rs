@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+/// Maximum number of add-ons attached to one subscription.
+pub const MAX_LINE_ITEMS: usize = 4;
+
+/// An add-on plan attached to a subscription, billed on the subscription's
+/// schedule and delegation.
+#[account]
+#[derive(InitSpace)]
+pub struct SubscriptionItem {
+    /// The subscription the add-on is attached to.
+    pub subscription: Pubkey,
+    /// The add-on plan (used in PDA seeds).
+    pub plan: Pubkey,
+    /// Locked-in amount charged per cycle (copied from the add-on plan).
+    pub amount: u64,
+    /// Unix timestamp the add-on was attached.
+    pub attached_at: i64,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+/// Billing snapshot of an attached add-on, itemized on receipts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct LineItem {
+    /// The add-on plan.
+    pub plan: Pubkey,
+    /// Amount charged per cycle, in tokens.
+    pub amount: u64,
+}
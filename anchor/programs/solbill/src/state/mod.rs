@@ -1,7 +1,21 @@
+pub mod affiliate;
+pub mod coupon;
+pub mod item;
+pub mod offer;
 pub mod plan;
+pub mod revenue_split;
+pub mod reward_pool;
+pub mod seat;
 pub mod service;
 pub mod subscription;
 
+pub use affiliate::*;
+pub use coupon::*;
+pub use item::*;
+pub use offer::*;
 pub use plan::*;
+pub use revenue_split::*;
+pub use reward_pool::*;
+pub use seat::*;
 pub use service::*;
 pub use subscription::*;
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+
+/// Prefix of every signed offer message, so the signature can't be replayed
+/// as anything else the service authority signs.
+pub const OFFER_DOMAIN: &[u8] = b"solbill:private-offer";
+
+/// Custom terms a merchant signs off-chain for a single subscriber.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PrivateOffer {
+    /// `ServiceAccount` whose authority signs the offer.
+    pub service: Pubkey,
+    /// Plan the subscription is created under.
+    pub plan: Pubkey,
+    /// The only wallet that may accept the offer.
+    pub subscriber: Pubkey,
+    /// Negotiated payment amount per interval (replaces the plan's).
+    pub amount: u64,
+    /// Negotiated billing interval in seconds (replaces the plan's).
+    pub interval: i64,
+    /// Unix timestamp after which the offer can no longer be accepted.
+    pub expires_at: i64,
+    /// Merchant-chosen number, unique per service, identifying the offer's
+    /// `OfferRedemption` marker.
+    pub nonce: u64,
+}
+
+/// Marks a private offer as accepted so it can't be accepted again, e.g.
+/// after the subscription is cancelled. Never closed.
+#[account]
+#[derive(InitSpace)]
+pub struct OfferRedemption {
+    /// `ServiceAccount` that signed the offer.
+    pub service: Pubkey,
+    /// Wallet that accepted the offer.
+    pub subscriber: Pubkey,
+    /// The offer's nonce (used in PDA seeds).
+    pub nonce: u64,
+    /// Unix timestamp of the redemption.
+    pub redeemed_at: i64,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+impl PrivateOffer {
+    /// The exact bytes the service authority signs.
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = OFFER_DOMAIN.to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+
+    /// Check the offer applies to this service, plan and subscriber at `now`.
+    pub fn check(
+        &self,
+        service: &Pubkey,
+        plan: &Pubkey,
+        subscriber: &Pubkey,
+        now: i64,
+    ) -> Result<()> {
+        require!(
+            self.service == *service && self.plan == *plan && self.subscriber == *subscriber,
+            SolBillError::OfferMismatch
+        );
+        require!(now < self.expires_at, SolBillError::OfferExpired);
+        require!(self.amount > 0, SolBillError::InvalidAmount);
+        require!(self.interval > 0, SolBillError::InvalidInterval);
+        Ok(())
+    }
+}
@@ -1,5 +1,335 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::SolBillError;
+use crate::oracle::{PriceData, PriceSource};
+use crate::state::{ServiceAccount, BPS_DENOMINATOR};
+
+/// Crank reward expressed as a share of each charge, clamped to a floor and ceiling.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct PercentageReward {
+    /// Share of the charge paid to the cranker, in basis points.
+    pub bps: u16,
+    /// Minimum reward (e.g. enough to cover transaction fees).
+    pub floor: u64,
+    /// Maximum reward (0 = uncapped).
+    pub ceiling: u64,
+}
+
+impl PercentageReward {
+    /// Reward for a charge of `amount`, never exceeding the charge itself.
+    pub fn reward_for(&self, amount: u64) -> Result<u64> {
+        let scaled = (amount as u128)
+            .checked_mul(self.bps as u128)
+            .ok_or(SolBillError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        let mut reward = u64::try_from(scaled).map_err(|_| SolBillError::Overflow)?;
+        reward = reward.max(self.floor);
+        if self.ceiling > 0 {
+            reward = reward.min(self.ceiling);
+        }
+        Ok(reward.min(amount))
+    }
+
+    /// Validate the policy against the plan amount it will apply to.
+    pub fn validate(&self, amount: u64) -> Result<()> {
+        require!(
+            self.bps > 0 && (self.bps as u64) < BPS_DENOMINATOR,
+            SolBillError::InvalidCrankReward
+        );
+        require!(self.floor < amount, SolBillError::InvalidCrankReward);
+        require!(
+            self.ceiling == 0 || (self.ceiling >= self.floor && self.ceiling < amount),
+            SolBillError::InvalidCrankReward
+        );
+        Ok(())
+    }
+}
+
+/// Dutch-auction style bonus that grows the longer a charge goes uncollected.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct RewardEscalation {
+    /// Tokens added to the reward for every elapsed `step_interval`.
+    pub step_amount: u64,
+    /// Seconds past `next_billing_timestamp` per escalation step.
+    pub step_interval: i64,
+    /// Upper bound on the total reward (base + bonus).
+    pub max_reward: u64,
+}
+
+impl RewardEscalation {
+    /// Total reward for a charge that is `overdue` seconds late.
+    pub fn escalate(&self, base_reward: u64, overdue: i64) -> u64 {
+        let steps = (overdue.max(0) / self.step_interval) as u64;
+        let bonus = steps.saturating_mul(self.step_amount);
+        base_reward
+            .saturating_add(bonus)
+            .min(self.max_reward.max(base_reward))
+    }
+
+    /// Validate the escalation against the plan amount it will apply to.
+    pub fn validate(&self, amount: u64) -> Result<()> {
+        require!(
+            self.step_amount > 0 && self.step_interval > 0,
+            SolBillError::InvalidRewardEscalation
+        );
+        require!(self.max_reward < amount, SolBillError::InvalidCrankReward);
+        Ok(())
+    }
+}
+
+/// How a late fee is computed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum LateFeeKind {
+    /// A fixed amount, in the same units as the plan's `amount`.
+    Flat(u64),
+    /// A share of the overdue charge, in basis points.
+    Bps(u16),
+}
+
+/// Fee added when a past-due subscription is finally collected.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct LateFee {
+    pub kind: LateFeeKind,
+    /// Upper bound on the fee (0 = uncapped).
+    pub max_fee: u64,
+}
+
+impl LateFee {
+    /// Late fee owed on an overdue charge of `amount`.
+    pub fn fee_for(&self, amount: u64) -> Result<u64> {
+        let fee = match self.kind {
+            LateFeeKind::Flat(fee) => fee,
+            LateFeeKind::Bps(bps) => {
+                let scaled = (amount as u128)
+                    .checked_mul(bps as u128)
+                    .ok_or(SolBillError::Overflow)?
+                    / BPS_DENOMINATOR as u128;
+                u64::try_from(scaled).map_err(|_| SolBillError::Overflow)?
+            }
+        };
+        Ok(if self.max_fee > 0 {
+            fee.min(self.max_fee)
+        } else {
+            fee
+        })
+    }
+
+    /// Whether the fee charges anything at all.
+    pub fn is_enabled(&self) -> bool {
+        match self.kind {
+            LateFeeKind::Flat(fee) => fee > 0,
+            LateFeeKind::Bps(bps) => bps > 0,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let LateFeeKind::Bps(bps) = self.kind {
+            require!(
+                (bps as u64) <= BPS_DENOMINATOR,
+                SolBillError::InvalidLateFee
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Maximum number of loyalty tiers on a plan.
+pub const MAX_LOYALTY_TIERS: usize = 4;
+
+/// Discount unlocked once a subscription has made enough payments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct LoyaltyTier {
+    /// Payments made (including the first) before the tier applies.
+    pub min_payments: u32,
+    /// Discount off the recurring amount, in basis points.
+    pub discount_bps: u16,
+}
+
+impl LoyaltyTier {
+    /// Tiers must be in strictly increasing `min_payments` order.
+    pub fn validate_all(tiers: &[LoyaltyTier]) -> Result<()> {
+        require!(
+            tiers.len() <= MAX_LOYALTY_TIERS,
+            SolBillError::InvalidLoyaltyTiers
+        );
+        for tier in tiers {
+            require!(
+                tier.min_payments > 0
+                    && tier.discount_bps > 0
+                    && (tier.discount_bps as u64) <= BPS_DENOMINATOR,
+                SolBillError::InvalidLoyaltyTiers
+            );
+        }
+        require!(
+            tiers
+                .windows(2)
+                .all(|pair| pair[0].min_payments < pair[1].min_payments),
+            SolBillError::InvalidLoyaltyTiers
+        );
+        Ok(())
+    }
+
+    /// Discount in basis points for a subscription with `payments_made`.
+    pub fn discount_for(tiers: &[LoyaltyTier], payments_made: u32) -> u16 {
+        tiers
+            .iter()
+            .rev()
+            .find(|tier| payments_made >= tier.min_payments)
+            .map_or(0, |tier| tier.discount_bps)
+    }
+}
+
+/// Bounds on the amount subscribers may choose on a pay-what-you-want plan.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct AmountRange {
+    /// Least a subscriber may pay per cycle.
+    pub min_amount: u64,
+    /// Most a subscriber may pay per cycle (0 = no ceiling).
+    pub max_amount: u64,
+}
+
+impl AmountRange {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.min_amount > 0 && (self.max_amount == 0 || self.max_amount >= self.min_amount),
+            SolBillError::InvalidAmountRange
+        );
+        Ok(())
+    }
+
+    /// Ensure a subscriber-chosen `amount` lies within the range.
+    pub fn check(&self, amount: u64) -> Result<()> {
+        require!(
+            amount >= self.min_amount && (self.max_amount == 0 || amount <= self.max_amount),
+            SolBillError::AmountOutOfRange
+        );
+        Ok(())
+    }
+}
+
+/// Maximum number of phases in a ramped pricing schedule.
+pub const MAX_PRICE_PHASES: usize = 4;
+
+/// One step of a ramped pricing schedule.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct PricePhase {
+    /// Amount charged per cycle during this phase.
+    pub amount: u64,
+    /// Billing interval in seconds during this phase.
+    pub interval: i64,
+    /// Payments (including the first) billed at this phase before moving on
+    /// (0 = for the rest of the subscription; last phase only).
+    pub cycles: u32,
+}
+
+impl PricePhase {
+    /// Every phase must charge something on a positive interval, and only the
+    /// last phase may run indefinitely.
+    pub fn validate_all(phases: &[PricePhase]) -> Result<()> {
+        require!(
+            phases.len() <= MAX_PRICE_PHASES,
+            SolBillError::InvalidPricePhases
+        );
+        for (index, phase) in phases.iter().enumerate() {
+            require!(
+                phase.amount > 0
+                    && phase.interval > 0
+                    && (phase.cycles > 0 || index == phases.len() - 1),
+                SolBillError::InvalidPricePhases
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Decimals of a quote-currency `amount` (micro-units, e.g. micro-USD).
+pub const QUOTE_DECIMALS: u32 = 6;
+
+/// Prices a plan in a quote currency, converted to tokens at collection time.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct QuotePricing {
+    /// Oracle format of `price_feed`.
+    pub source: PriceSource,
+    /// Price account quoting the accepted mint in the quote currency.
+    pub price_feed: Pubkey,
+    /// Oldest acceptable price, in seconds.
+    pub max_staleness: i64,
+    /// Widest acceptable confidence interval, in basis points of the price.
+    pub max_confidence_bps: u16,
+}
+
+impl QuotePricing {
+    /// Decode `feed` with this plan's oracle reader.
+    pub fn read_price(&self, feed: &AccountInfo) -> Result<PriceData> {
+        self.source.read_price(feed)
+    }
+
+    /// Reject stale or low-confidence prices.
+    pub fn check(&self, price: &PriceData, now: i64) -> Result<()> {
+        require!(price.price > 0, SolBillError::InvalidPriceFeed);
+        require!(
+            now.saturating_sub(price.publish_time) <= self.max_staleness,
+            SolBillError::StalePrice
+        );
+        let conf_bps = (price.conf as u128) * BPS_DENOMINATOR as u128 / price.price as u128;
+        require!(
+            conf_bps <= self.max_confidence_bps as u128,
+            SolBillError::PriceConfidenceTooWide
+        );
+        Ok(())
+    }
+
+    /// Tokens (in base units of a mint with `mint_decimals`) worth
+    /// `quote_amount`, rounded up in the merchant's favour.
+    pub fn to_tokens(
+        &self,
+        quote_amount: u64,
+        price: &PriceData,
+        mint_decimals: u8,
+    ) -> Result<u64> {
+        let pow10 = |exp: i64| -> Result<u128> {
+            10u128
+                .checked_pow(u32::try_from(exp).map_err(|_| SolBillError::Overflow)?)
+                .ok_or_else(|| error!(SolBillError::Overflow))
+        };
+        // tokens = quote_amount * 10^mint_decimals / (price * 10^exponent * 10^QUOTE_DECIMALS)
+        let scale = mint_decimals as i64 - price.exponent as i64 - QUOTE_DECIMALS as i64;
+        let quote = quote_amount as u128;
+        let price = price.price as u128;
+        let tokens = if scale >= 0 {
+            quote
+                .checked_mul(pow10(scale)?)
+                .ok_or(SolBillError::Overflow)?
+                .div_ceil(price)
+        } else {
+            quote.div_ceil(
+                price
+                    .checked_mul(pow10(-scale)?)
+                    .ok_or(SolBillError::Overflow)?,
+            )
+        };
+        u64::try_from(tokens).map_err(|_| error!(SolBillError::Overflow))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_staleness > 0 && self.max_confidence_bps > 0,
+            SolBillError::InvalidPriceFeed
+        );
+        Ok(())
+    }
+}
+
+/// Who bears Token-2022 transfer fees when the accepted mint charges them.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub enum TransferFeeMode {
+    /// The subscriber is debited `amount`; fees come out of the merchant's share.
+    #[default]
+    MerchantAbsorbs,
+    /// The subscriber is debited a grossed-up amount so payees receive in full.
+    SubscriberPays,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct PlanAccount {
@@ -10,6 +340,7 @@ pub struct PlanAccount {
     /// Payment amount per interval (smallest token unit, e.g. 1_000_000 = 1 USDC).
     pub amount: u64,
     /// Reward paid to the cranker (caller) for processing payment.
+    /// Ignored when `percentage_reward` is set.
     pub crank_reward: u64,
     /// Billing interval in seconds (e.g. 2_592_000 = 30 days).
     pub interval: i64,
@@ -21,6 +352,107 @@ pub struct PlanAccount {
     pub plan_index: u16,
     /// Limit on number of billing cycles (0 = infinite, 1 = one-time).
     pub max_billing_cycles: u64,
+    /// Plan-specific `RevenueSplit` (takes precedence over the service's).
+    pub revenue_split: Option<Pubkey>,
+    /// Crank reward as a share of the charge (overrides `crank_reward`).
+    pub percentage_reward: Option<PercentageReward>,
+    /// Bonus added to the crank reward while a charge is overdue.
+    pub reward_escalation: Option<RewardEscalation>,
+    /// Seconds after the due date during which only the merchant's collector
+    /// may collect, without a crank reward (0 = public immediately).
+    pub exclusive_window: i64,
+    /// Billed in lamports from a subscription escrow instead of the accepted mint.
+    pub native_sol: bool,
+    /// Who bears the mint's transfer fee, if it has one.
+    pub transfer_fee_mode: TransferFeeMode,
+    /// Mint this plan is priced in, overriding the service's accepted mint.
+    pub accepted_mint: Option<Pubkey>,
+    /// Treasury for `accepted_mint`, overriding the service's treasury.
+    pub treasury: Option<Pubkey>,
+    /// Quote-currency pricing; when set, `amount` and `crank_reward` are in
+    /// `QUOTE_DECIMALS` quote units and converted at each charge.
+    pub quote_pricing: Option<QuotePricing>,
+    /// One-time activation fee charged with the first period (0 = none).
+    /// In the same units as `amount`.
+    pub setup_fee: u64,
+    /// Fee added when collecting a past-due subscription.
+    pub late_fee: Option<LateFee>,
+    /// Tenure discounts, by payments made.
+    #[max_len(MAX_LOYALTY_TIERS)]
+    pub loyalty_tiers: Vec<LoyaltyTier>,
+    /// Ramped pricing schedule; when set, its first phase is mirrored in
+    /// `amount` and `interval`.
+    #[max_len(MAX_PRICE_PHASES)]
+    pub price_phases: Vec<PricePhase>,
+    /// Subscriber-chosen pricing; when set, `amount` is the floor.
+    pub pay_what_you_want: Option<AmountRange>,
+    /// Fewest seats a subscription may hold; `amount` is charged per seat.
+    pub min_quantity: u32,
+    /// Most seats a subscription may hold (0 = no limit).
+    pub max_quantity: u32,
     /// PDA bump seed.
     pub bump: u8,
 }
+
+impl PlanAccount {
+    /// The mint subscriptions to this plan pay in.
+    pub fn mint_for(&self, service: &ServiceAccount) -> Pubkey {
+        self.accepted_mint.unwrap_or(service.accepted_mint)
+    }
+
+    /// The token account receiving this plan's payments.
+    pub fn treasury_for(&self, service: &ServiceAccount) -> Pubkey {
+        self.treasury.unwrap_or(service.treasury)
+    }
+
+    /// Ensure a subscription may hold `quantity` seats on this plan.
+    pub fn check_quantity(&self, quantity: u32) -> Result<()> {
+        require!(
+            quantity >= self.min_quantity
+                && (self.max_quantity == 0 || quantity <= self.max_quantity),
+            SolBillError::InvalidQuantity
+        );
+        Ok(())
+    }
+
+    /// Whether this is a free tier (a zero amount), which is never charged.
+    pub fn is_free(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Smallest per-cycle price of the plan: its cheapest price phase, or
+    /// the plan amount (a pay-what-you-want plan's floor).
+    pub fn lowest_amount(&self) -> u64 {
+        self.price_phases
+            .iter()
+            .map(|phase| phase.amount)
+            .min()
+            .unwrap_or(self.amount)
+    }
+
+    /// Ensure no crank reward policy can take a whole charge of `amount`.
+    /// Free plans (a zero amount) pay no crank reward at all. Where the mint
+    /// is known, `amount` is what the charge nets after its transfer fee, as
+    /// rewards are sent grossed up for it.
+    pub fn validate_rewards(&self, amount: u64) -> Result<()> {
+        require!(
+            self.crank_reward == 0 || self.crank_reward < amount,
+            SolBillError::InvalidCrankReward
+        );
+        if let Some(policy) = &self.percentage_reward {
+            policy.validate(amount)?;
+        }
+        if let Some(escalation) = &self.reward_escalation {
+            escalation.validate(amount)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a charge due at `next_billing` is still inside the merchant's
+    /// exclusive collection window at `now`.
+    pub fn in_exclusive_window(&self, next_billing: i64, now: i64) -> bool {
+        self.exclusive_window > 0
+            && now >= next_billing
+            && now < next_billing.saturating_add(self.exclusive_window)
+    }
+}
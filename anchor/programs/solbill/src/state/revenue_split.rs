@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::SolBillError;
+
+/// Maximum number of payees in a single revenue split.
+pub const MAX_SPLIT_RECIPIENTS: usize = 5;
+
+/// Basis points representing 100% of a payment.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// A single payee within a revenue split.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct SplitRecipient {
+    /// Token account (of the service's accepted mint) receiving this share.
+    pub token_account: Pubkey,
+    /// Share of the net payment in basis points (10_000 = 100%).
+    pub share_bps: u16,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct RevenueSplit {
+    /// Parent `ServiceAccount` pubkey.
+    pub service: Pubkey,
+    /// The service or plan this split is attached to (used in PDA seeds).
+    pub target: Pubkey,
+    /// Payees and their basis-point shares (must sum to 10_000).
+    #[max_len(MAX_SPLIT_RECIPIENTS)]
+    pub recipients: Vec<SplitRecipient>,
+    /// Index into `recipients` of the payee that receives rounding dust.
+    pub primary_index: u8,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+impl RevenueSplit {
+    /// Split `net` across the recipients, in recipient order.
+    /// Any remainder left by integer division goes to the primary payee.
+    pub fn allocate(&self, net: u64) -> Result<Vec<u64>> {
+        let mut shares = Vec::with_capacity(self.recipients.len());
+        let mut allocated: u64 = 0;
+
+        for recipient in self.recipients.iter() {
+            let share = (net as u128)
+                .checked_mul(recipient.share_bps as u128)
+                .ok_or(SolBillError::Overflow)?
+                / BPS_DENOMINATOR as u128;
+            let share = u64::try_from(share).map_err(|_| SolBillError::Overflow)?;
+            allocated = allocated.checked_add(share).ok_or(SolBillError::Overflow)?;
+            shares.push(share);
+        }
+
+        let dust = net.checked_sub(allocated).ok_or(SolBillError::Overflow)?;
+        let primary = shares
+            .get_mut(self.primary_index as usize)
+            .ok_or(SolBillError::InvalidRevenueSplit)?;
+        *primary = primary.checked_add(dust).ok_or(SolBillError::Overflow)?;
+
+        Ok(shares)
+    }
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct RewardPool {
+    /// Parent `ServiceAccount` pubkey.
+    pub service: Pubkey,
+    /// Lamports paid to the cranker for each successful collection.
+    pub lamports_per_crank: u64,
+    /// Lamports paid for maintenance cranks (past-due marking, expiry).
+    pub maintenance_reward: u64,
+    /// Total lamports paid out to crankers.
+    pub total_paid: u64,
+    /// PDA bump seed.
+    pub bump: u8,
+}
+
+impl RewardPool {
+    /// Lamports held above the rent-exempt minimum.
+    pub fn available_lamports(info: &AccountInfo) -> Result<u64> {
+        let rent_exempt = Rent::get()?.minimum_balance(info.data_len());
+        Ok(info.lamports().saturating_sub(rent_exempt))
+    }
+}
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+/// A wallet holding one of a subscription's seats. Access checks for the
+/// member resolve to the owning subscription's status.
+#[account]
+#[derive(InitSpace)]
+pub struct SeatAssignment {
+    /// The subscription whose seat this is.
+    pub subscription: Pubkey,
+    /// The member's wallet (used in PDA seeds).
+    pub member: Pubkey,
+    /// Unix timestamp of the assignment.
+    pub assigned_at: i64,
+    /// The subscription's `generation` when the seat was assigned.
+    pub generation: u64,
+    /// PDA bump seed.
+    pub bump: u8,
+}
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::SolBillError;
+
 #[account]
 #[derive(InitSpace)]
 pub struct ServiceAccount {
@@ -15,6 +17,32 @@ pub struct ServiceAccount {
     pub subscriber_count: u32,
     /// Unix timestamp of service creation.
     pub created_at: i64,
+    /// Service-wide `RevenueSplit` applied when a plan has none of its own.
+    pub revenue_split: Option<Pubkey>,
+    /// `RewardPool` paying crankers in lamports instead of the payment mint.
+    pub reward_pool: Option<Pubkey>,
+    /// Merchant-run collector allowed to collect during plan exclusivity windows.
+    pub collector: Option<Pubkey>,
+    /// Wallet receiving lamport payments for native SOL plans.
+    pub sol_treasury: Option<Pubkey>,
+    /// Subscriptions ever created; numbers each one's `generation`.
+    pub subscriptions_created: u64,
     /// PDA bump seed.
     pub bump: u8,
 }
+
+impl ServiceAccount {
+    /// Number a newly created subscription.
+    pub fn next_generation(&mut self) -> Result<u64> {
+        self.subscriptions_created = self
+            .subscriptions_created
+            .checked_add(1)
+            .ok_or(SolBillError::Overflow)?;
+        Ok(self.subscriptions_created)
+    }
+
+    /// Whether `key` is the merchant itself or its designated collector.
+    pub fn is_merchant_collector(&self, key: &Pubkey) -> bool {
+        *key == self.authority || self.collector.as_ref() == Some(key)
+    }
+}
@@ -1,5 +1,13 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::SolBillError;
+use crate::oracle::PriceData;
+use crate::state::{
+    AppliedDiscount, Coupon, LateFee, LineItem, LoyaltyTier, PercentageReward, PlanAccount,
+    PricePhase, QuotePricing, Referral, RewardEscalation, TransferFeeMode, BPS_DENOMINATOR,
+    MAX_LINE_ITEMS, MAX_LOYALTY_TIERS, MAX_PRICE_PHASES, MAX_REDEEMED_COUPONS,
+};
+
 /// Subscription lifecycle states.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
 pub enum SubscriptionStatus {
@@ -10,6 +18,43 @@ pub enum SubscriptionStatus {
     Completed,
 }
 
+/// Subscriber-imposed limits on what may be charged (0 = no limit).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace)]
+pub struct SpendingCaps {
+    /// Most that a single charge may take.
+    pub per_charge: u64,
+    /// Most that may be charged within one spending period.
+    pub per_period: u64,
+    /// Length of a spending period in seconds (required with `per_period`).
+    pub period: i64,
+    /// Most that may ever be charged over the subscription's lifetime.
+    pub lifetime: u64,
+}
+
+impl SpendingCaps {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.per_period == 0 || self.period > 0,
+            SolBillError::InvalidSpendingCaps
+        );
+        Ok(())
+    }
+
+    /// Whether recurring charges of `amount` could ever pass the per-charge
+    /// and per-period caps.
+    pub fn admits(&self, amount: u64) -> Result<()> {
+        require!(
+            self.per_charge == 0 || amount <= self.per_charge,
+            SolBillError::PerChargeCapExceeded
+        );
+        require!(
+            self.per_period == 0 || amount <= self.per_period,
+            SolBillError::PeriodCapExceeded
+        );
+        Ok(())
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct SubscriptionAccount {
@@ -21,8 +66,15 @@ pub struct SubscriptionAccount {
     pub original_plan: Pubkey,
     /// Current plan for billing (can change via change_plan).
     pub plan: Pubkey,
-    /// The subscriber's token account (source of funds).
+    /// The subscriber's token account (source of funds), or the lamport
+    /// escrow PDA for native SOL subscriptions.
     pub subscriber_token_account: Pubkey,
+    /// Mint the subscription pays in (copied from Plan at creation; unset for
+    /// native SOL subscriptions).
+    pub mint: Pubkey,
+    /// Token account receiving the subscription's payments (copied from Plan
+    /// at creation; unset for native SOL subscriptions).
+    pub treasury: Pubkey,
     /// Locked-in payment amount (copied from Plan at creation).
     pub amount: u64,
     /// Reward paid to the cranker (copied from Plan at creation).
@@ -41,6 +93,524 @@ pub struct SubscriptionAccount {
     pub payments_made: u32,
     /// Limit on number of billing cycles (0 = infinite).
     pub max_billing_cycles: u64,
+    /// Percentage-based crank reward (copied from Plan; overrides `crank_reward`).
+    pub percentage_reward: Option<PercentageReward>,
+    /// Overdue reward escalation (copied from Plan).
+    pub reward_escalation: Option<RewardEscalation>,
+    /// Billed in lamports from the escrow PDA (copied from Plan at creation).
+    pub native_sol: bool,
+    /// Who bears the mint's transfer fee (copied from Plan).
+    pub transfer_fee_mode: TransferFeeMode,
+    /// Quote-currency pricing (copied from Plan).
+    pub quote_pricing: Option<QuotePricing>,
+    /// Most tokens the subscriber accepts paying for a single charge (0 = no limit).
+    pub max_token_amount: u64,
+    /// Subscriber-imposed spending limits.
+    pub spending_caps: SpendingCaps,
+    /// Start of the current spending period.
+    pub period_started_at: i64,
+    /// Amount charged since `period_started_at`.
+    pub period_spent: u64,
+    /// Amount charged over the subscription's lifetime.
+    pub lifetime_spent: u64,
+    /// One-off charges added by the merchant, in tokens, collected with the
+    /// next recurring charge.
+    pub pending_charges: u64,
+    /// Fee added when collecting while past due (copied from Plan).
+    pub late_fee: Option<LateFee>,
+    /// Coupon discount applied to upcoming charges.
+    pub discount: Option<AppliedDiscount>,
+    /// Affiliate referral still paying out rewards.
+    pub referral: Option<Referral>,
+    /// Every coupon this subscription has redeemed; none can be redeemed twice.
+    #[max_len(MAX_REDEEMED_COUPONS)]
+    pub redeemed_coupons: Vec<Pubkey>,
+    /// Tenure discounts (copied from Plan).
+    #[max_len(MAX_LOYALTY_TIERS)]
+    pub loyalty_tiers: Vec<LoyaltyTier>,
+    /// Ramped pricing schedule (copied from Plan).
+    #[max_len(MAX_PRICE_PHASES)]
+    pub price_phases: Vec<PricePhase>,
+    /// Index of the current phase in `price_phases`.
+    pub phase_index: u8,
+    /// Payments made in the current phase.
+    pub phase_payments: u32,
+    /// Set when a phase change raised the price beyond the approved delegation.
+    pub reapproval_required: bool,
+    /// Seats billed; each charge is `amount × quantity`.
+    pub quantity: u32,
+    /// Prorated credit from removed seats, in tokens, taken off upcoming
+    /// recurring charges.
+    pub credit_balance: u64,
+    /// Seats assigned to member wallets (at most `quantity`).
+    pub assigned_seats: u32,
+    /// Sequence number of this subscription within its service. Seats record
+    /// it, so seats of an earlier subscription at this address never count.
+    pub generation: u64,
+    /// Add-ons billed with each recurring charge.
+    #[max_len(MAX_LINE_ITEMS)]
+    pub items: Vec<LineItem>,
+    /// Terms negotiated in a signed private offer, whose amount is fixed.
+    pub from_offer: bool,
     /// PDA bump seed.
     pub bump: u8,
 }
+
+impl SubscriptionAccount {
+    /// Lock in the plan's billing terms at creation.
+    pub fn lock_in_terms(&mut self, plan: &PlanAccount) {
+        self.amount = plan.amount;
+        self.crank_reward = plan.crank_reward;
+        self.percentage_reward = plan.percentage_reward;
+        self.reward_escalation = plan.reward_escalation;
+        self.interval = plan.interval;
+        self.max_billing_cycles = plan.max_billing_cycles;
+        self.native_sol = plan.native_sol;
+        self.transfer_fee_mode = plan.transfer_fee_mode;
+        self.quote_pricing = plan.quote_pricing;
+        self.late_fee = plan.late_fee;
+        self.loyalty_tiers = plan.loyalty_tiers.clone();
+        self.price_phases = plan.price_phases.clone();
+        self.reapproval_required = false;
+        self.from_offer = false;
+        self.quantity = plan.min_quantity;
+        if !self.price_phases.is_empty() {
+            self.enter_phase(0);
+        }
+    }
+
+    /// Bill at phase `index` of the pricing schedule from now on.
+    pub fn enter_phase(&mut self, index: usize) {
+        let phase = self.price_phases[index];
+        self.phase_index = index as u8;
+        self.phase_payments = 0;
+        self.amount = phase.amount;
+        self.interval = phase.interval;
+    }
+
+    /// Count a payment towards the current phase, moving to the next phase once
+    /// its cycles are paid. Returns whether the price went up.
+    pub fn advance_phase(&mut self) -> Result<bool> {
+        let index = self.phase_index as usize;
+        let Some(phase) = self.price_phases.get(index).copied() else {
+            return Ok(false);
+        };
+        self.phase_payments = self
+            .phase_payments
+            .checked_add(1)
+            .ok_or(SolBillError::Overflow)?;
+        if phase.cycles == 0
+            || self.phase_payments < phase.cycles
+            || index + 1 >= self.price_phases.len()
+        {
+            return Ok(false);
+        }
+        let previous = self.amount;
+        self.enter_phase(index + 1);
+        msg!(
+            "Entered price phase {}: {} every {}s",
+            self.phase_index,
+            self.amount,
+            self.interval
+        );
+        Ok(self.amount > previous)
+    }
+
+    /// Loyalty discount (in basis points) earned by the payments made so far.
+    pub fn loyalty_discount_bps(&self) -> u16 {
+        LoyaltyTier::discount_for(&self.loyalty_tiers, self.payments_made)
+    }
+
+    /// Recurring price for all seats, before discounts.
+    pub fn recurring_amount(&self) -> Result<u64> {
+        let recurring = self
+            .amount
+            .checked_mul(self.quantity as u64)
+            .ok_or(SolBillError::Overflow)?;
+        Ok(recurring)
+    }
+
+    /// Total of the attached add-ons, in tokens.
+    pub fn items_amount(&self) -> Result<u64> {
+        self.items.iter().try_fold(0u64, |total, item| {
+            total
+                .checked_add(item.amount)
+                .ok_or_else(|| error!(SolBillError::Overflow))
+        })
+    }
+
+    /// Tokens the delegation must cover for the next charge: the cap for
+    /// quote-priced subscriptions, otherwise every seat, add-on and pending
+    /// charge, plus any late fee. The late fee is covered even while active,
+    /// so a charge that goes past due can be collected once the subscriber
+    /// tops up, without approving again.
+    pub fn delegation_amount(&self) -> Result<u64> {
+        if self.quote_pricing.is_some() {
+            return Ok(self.max_token_amount);
+        }
+        let late_fee = match self.late_fee {
+            Some(late_fee) => late_fee.fee_for(self.effective_amount()?)?,
+            None => 0,
+        };
+        self.recurring_amount()?
+            .checked_add(self.items_amount()?)
+            .and_then(|total| total.checked_add(self.pending_charges))
+            .and_then(|total| total.checked_add(late_fee))
+            .ok_or_else(|| error!(SolBillError::Overflow))
+    }
+
+    /// Tokens the next flat-priced collection charges, as `collect_payment`
+    /// prices it: the discounted recurring amount less any credit, plus
+    /// add-ons, pending one-off charges and any late fee.
+    pub fn next_charge(&self) -> Result<u64> {
+        let amount = self.effective_amount()?;
+        let late_fee = self.late_fee_for(amount)?;
+        let recurring = amount - self.credit_balance.min(amount);
+        recurring
+            .checked_add(self.items_amount()?)
+            .and_then(|total| total.checked_add(self.pending_charges))
+            .and_then(|total| total.checked_add(late_fee))
+            .ok_or_else(|| error!(SolBillError::Overflow))
+    }
+
+    /// Lamports the next native SOL collection charges, as
+    /// `collect_native_payment` prices it: the discounted recurring amount
+    /// plus any late fee.
+    pub fn next_native_charge(&self) -> Result<u64> {
+        let amount = self.effective_amount()?;
+        amount
+            .checked_add(self.late_fee_for(amount)?)
+            .ok_or_else(|| error!(SolBillError::Overflow))
+    }
+
+    /// Recurring price of the next charge after loyalty and coupon discounts.
+    pub fn effective_amount(&self) -> Result<u64> {
+        let recurring = self.recurring_amount()?;
+        let loyalty_off = (recurring as u128)
+            .checked_mul(self.loyalty_discount_bps() as u128)
+            .ok_or(SolBillError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        let amount = recurring.saturating_sub(loyalty_off as u64);
+        self.discounted(amount)
+    }
+
+    /// Price of `seats` seats for the rest of the current period at `now`.
+    pub fn prorate(&self, seats: u32, now: i64) -> Result<u64> {
+        let remaining = self
+            .next_billing_timestamp
+            .saturating_sub(now)
+            .clamp(0, self.interval);
+        let prorated = (self.amount as u128)
+            .checked_mul(seats as u128)
+            .and_then(|total| total.checked_mul(remaining as u128))
+            .ok_or(SolBillError::Overflow)?
+            / self.interval as u128;
+        let prorated = u64::try_from(prorated).map_err(|_| SolBillError::Overflow)?;
+        Ok(prorated)
+    }
+
+    /// `amount` after any redeemed coupon discount.
+    pub fn discounted(&self, amount: u64) -> Result<u64> {
+        match &self.discount {
+            Some(applied) => applied.discount.apply(amount),
+            None => Ok(amount),
+        }
+    }
+
+    /// Redeem the coupon at `coupon_key` for `plan` at `now`, replacing any
+    /// current discount. Each coupon applies to a subscription only once.
+    pub fn redeem_coupon(
+        &mut self,
+        coupon_key: Pubkey,
+        coupon: &mut Coupon,
+        plan: &Pubkey,
+        now: i64,
+    ) -> Result<()> {
+        require!(
+            !self.redeemed_coupons.contains(&coupon_key),
+            SolBillError::CouponAlreadyRedeemed
+        );
+        require!(
+            self.redeemed_coupons.len() < MAX_REDEEMED_COUPONS,
+            SolBillError::TooManyCoupons
+        );
+        self.discount = Some(coupon.redeem(coupon_key, plan, now)?);
+        self.redeemed_coupons.push(coupon_key);
+        Ok(())
+    }
+
+    /// Count one discounted charge, dropping the discount once it runs out.
+    pub fn consume_discount(&mut self) {
+        if let Some(applied) = self.discount.as_mut() {
+            if let Some(remaining) = applied.remaining_cycles.as_mut() {
+                *remaining = remaining.saturating_sub(1);
+                if *remaining == 0 {
+                    self.discount = None;
+                }
+            }
+        }
+    }
+
+    /// Count one rewarded collection, dropping the referral once it is paid out.
+    pub fn consume_referral(&mut self) {
+        if let Some(referral) = self.referral.as_mut() {
+            referral.remaining_payouts = referral.remaining_payouts.saturating_sub(1);
+            if referral.remaining_payouts == 0 {
+                self.referral = None;
+            }
+        }
+    }
+
+    /// Late fee owed on top of an overdue charge of `amount`, in the same
+    /// units. Zero unless the subscription has been marked past due.
+    pub fn late_fee_for(&self, amount: u64) -> Result<u64> {
+        match self.late_fee {
+            Some(late_fee) if self.status == SubscriptionStatus::PastDue => {
+                late_fee.fee_for(amount)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Tokens owed for a plan-denominated `amount`: converted at `price` for
+    /// quote-priced subscriptions, unchanged otherwise.
+    pub fn to_token_amount(
+        &self,
+        amount: u64,
+        price: Option<&PriceData>,
+        mint_decimals: u8,
+    ) -> Result<u64> {
+        match (&self.quote_pricing, price) {
+            (Some(pricing), Some(price)) => pricing.to_tokens(amount, price, mint_decimals),
+            (Some(_), None) => err!(SolBillError::InvalidPriceFeed),
+            (None, _) => Ok(amount),
+        }
+    }
+
+    /// Refuse charges above the subscriber's maximum token amount.
+    pub fn check_max_token_amount(&self, charge: u64) -> Result<()> {
+        require!(
+            self.max_token_amount == 0 || charge <= self.max_token_amount,
+            SolBillError::MaxTokenAmountExceeded
+        );
+        Ok(())
+    }
+
+    /// Refuse a charge of `charge` at `now` that would break a spending cap.
+    pub fn check_spending_caps(&self, charge: u64, now: i64) -> Result<()> {
+        let caps = &self.spending_caps;
+        require!(
+            caps.per_charge == 0 || charge <= caps.per_charge,
+            SolBillError::PerChargeCapExceeded
+        );
+        let period_spent = if self.in_spending_period(now) {
+            self.period_spent
+        } else {
+            0
+        };
+        require!(
+            caps.per_period == 0 || period_spent.saturating_add(charge) <= caps.per_period,
+            SolBillError::PeriodCapExceeded
+        );
+        require!(
+            caps.lifetime == 0 || self.lifetime_spent.saturating_add(charge) <= caps.lifetime,
+            SolBillError::LifetimeCapExceeded
+        );
+        Ok(())
+    }
+
+    /// Count a charge of `charge` at `now` towards the spending caps.
+    pub fn record_spend(&mut self, charge: u64, now: i64) -> Result<()> {
+        if self.in_spending_period(now) {
+            self.period_spent = self
+                .period_spent
+                .checked_add(charge)
+                .ok_or(SolBillError::Overflow)?;
+        } else {
+            self.period_started_at = now;
+            self.period_spent = charge;
+        }
+        self.lifetime_spent = self
+            .lifetime_spent
+            .checked_add(charge)
+            .ok_or(SolBillError::Overflow)?;
+        Ok(())
+    }
+
+    fn in_spending_period(&self, now: i64) -> bool {
+        self.spending_caps.period > 0
+            && now
+                < self
+                    .period_started_at
+                    .saturating_add(self.spending_caps.period)
+    }
+
+    /// Whether the subscriber (and any seat members) should currently be
+    /// granted access.
+    pub fn has_access(&self) -> bool {
+        matches!(
+            self.status,
+            SubscriptionStatus::Active | SubscriptionStatus::PastDue
+        )
+    }
+
+    /// Whether this subscription is on a free tier.
+    pub fn is_free(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Set the status and first due date after the upfront payment at `now`.
+    pub fn start_billing(&mut self, now: i64) -> Result<()> {
+        self.created_at = now;
+
+        // Free tiers stay active without ever becoming due
+        if self.is_free() {
+            self.payments_made = 0;
+            self.status = SubscriptionStatus::Active;
+            self.next_billing_timestamp = i64::MAX;
+            msg!("Free plan. Status Active, never due.");
+            return Ok(());
+        }
+
+        self.payments_made = 1;
+        self.last_payment_timestamp = now;
+
+        msg!(
+            "Creating subscription. Plan max_cycles: {}",
+            self.max_billing_cycles
+        );
+
+        // Logic for One-Time Payments vs Recurring
+        if self.max_billing_cycles > 0 {
+            if self.max_billing_cycles == 1 {
+                // If it's a one-time payment, we set the status to Completed immediately
+                // because the user pays upfront when subscribing.
+                self.status = SubscriptionStatus::Completed;
+                // Prevent future billing
+                self.next_billing_timestamp = i64::MAX;
+                msg!("One-time payment plan. Status set to Completed.");
+            } else {
+                // It's a finite recurring plan (e.g. 3 months)
+                self.status = SubscriptionStatus::Active;
+                self.next_billing_timestamp = now
+                    .checked_add(self.interval)
+                    .ok_or(SolBillError::Overflow)?;
+                msg!(
+                    "Finite plan ({} cycles). Status Active. Next bill: {}",
+                    self.max_billing_cycles,
+                    self.next_billing_timestamp
+                );
+            }
+        } else {
+            // Infinite recurring
+            self.status = SubscriptionStatus::Active;
+            self.next_billing_timestamp = now
+                .checked_add(self.interval)
+                .ok_or(SolBillError::Overflow)?;
+            msg!(
+                "Infinite plan. Status Active. Next bill: {}",
+                self.next_billing_timestamp
+            );
+        }
+        Ok(())
+    }
+
+    /// Guards shared by every collection path.
+    pub fn check_collectable(&self, now: i64) -> Result<()> {
+        // Must be active or past due
+        require!(
+            self.status == SubscriptionStatus::Active || self.status == SubscriptionStatus::PastDue,
+            SolBillError::SubscriptionNotActive,
+        );
+
+        // Timing enforcement: cannot bill before due date
+        require!(
+            now >= self.next_billing_timestamp,
+            SolBillError::BillingNotDue
+        );
+
+        // A price rise from a pricing phase needs a fresh delegation first
+        require!(!self.reapproval_required, SolBillError::ReapprovalRequired);
+
+        // Check for max billing cycles limit BEFORE collecting
+        // This should not happen if status is correctly managed,
+        // but as a safety guard against race conditions or manual errors.
+        if self.max_billing_cycles > 0 && self.payments_made >= self.max_billing_cycles as u32 {
+            return err!(SolBillError::SubscriptionCompleted);
+        }
+        Ok(())
+    }
+
+    /// Advance the billing schedule after a successful collection of `charge` at `now`.
+    pub fn record_payment(&mut self, charge: u64, now: i64) -> Result<()> {
+        self.last_payment_timestamp = now;
+        self.record_spend(charge, now)?;
+        self.consume_discount();
+
+        // Increment payments made
+        self.payments_made = self
+            .payments_made
+            .checked_add(1)
+            .ok_or(SolBillError::Overflow)?;
+
+        msg!(
+            "Payment collected. Total payments made: {}",
+            self.payments_made
+        );
+
+        // Check if we hit the limit
+        if self.max_billing_cycles > 0 {
+            msg!(
+                "Checking max cycles: {}/{}",
+                self.payments_made,
+                self.max_billing_cycles
+            );
+            if self.payments_made >= self.max_billing_cycles as u32 {
+                self.status = SubscriptionStatus::Completed;
+                // Prevent further billing
+                self.next_billing_timestamp = i64::MAX;
+                msg!("Max cycles reached. Status set to Completed.");
+            } else {
+                // Not yet completed, schedule next
+                self.next_billing_timestamp = now
+                    .checked_add(self.interval)
+                    .ok_or(SolBillError::Overflow)?;
+                self.status = SubscriptionStatus::Active;
+                msg!(
+                    "Plan continues. Next billing: {}",
+                    self.next_billing_timestamp
+                );
+            }
+        } else {
+            // Infinite
+            self.next_billing_timestamp = now
+                .checked_add(self.interval)
+                .ok_or(SolBillError::Overflow)?;
+            self.status = SubscriptionStatus::Active;
+            msg!(
+                "Infinite plan continues. Next billing: {}",
+                self.next_billing_timestamp
+            );
+        }
+
+        // The next charge may fall in a new pricing phase; escrowed lamports
+        // need no approval
+        if self.advance_phase()? && !self.native_sol {
+            self.reapproval_required = true;
+        }
+        Ok(())
+    }
+
+    /// Crank reward owed for collecting a charge of `amount` at time `now`.
+    pub fn crank_reward_for(&self, amount: u64, now: i64) -> Result<u64> {
+        let mut reward = match self.percentage_reward {
+            Some(policy) => policy.reward_for(amount)?,
+            None => self.crank_reward,
+        };
+        if let Some(escalation) = self.reward_escalation {
+            let overdue = now.saturating_sub(self.next_billing_timestamp);
+            reward = escalation.escalate(reward, overdue);
+        }
+        Ok(reward.min(amount))
+    }
+}
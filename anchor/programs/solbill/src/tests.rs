@@ -1,6 +1,9 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
+    use crate::state::{SubscriptionAccount, SubscriptionStatus};
     use crate::ID as PROGRAM_ID;
+    use anchor_lang::{AccountDeserialize, AccountSerialize};
     use litesvm::LiteSVM;
     use solana_sdk::program_pack::Pack;
     use solana_sdk::{
@@ -43,6 +46,27 @@ mod tests {
         )
     }
 
+    fn get_reward_pool_pda(service: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"reward_pool", service.as_ref()], &PROGRAM_ID)
+    }
+
+    fn get_split_pda(target: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"split", target.as_ref()], &PROGRAM_ID)
+    }
+
+    fn get_subscription(svm: &LiteSVM, address: &Pubkey) -> SubscriptionAccount {
+        let data = svm.get_account(address).unwrap().data;
+        SubscriptionAccount::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn set_subscription(svm: &mut LiteSVM, address: &Pubkey, subscription: &SubscriptionAccount) {
+        let mut account = svm.get_account(address).unwrap();
+        let mut data = Vec::with_capacity(account.data.len());
+        subscription.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        svm.set_account(*address, account).unwrap();
+    }
+
     #[test]
     fn test_initialization_and_plan_creation() {
         let mut svm = LiteSVM::new();
@@ -146,6 +170,8 @@ mod tests {
         plan_ix_data.extend_from_slice(&interval.to_le_bytes());
         plan_ix_data.extend_from_slice(&grace_period.to_le_bytes());
         plan_ix_data.extend_from_slice(&0u64.to_le_bytes()); // max_billing_cycles = 0 (infinite)
+        plan_ix_data.push(0); // None percentage_reward
+        plan_ix_data.push(0); // native_sol false
 
         let plan_ix = Instruction {
             program_id: PROGRAM_ID,
@@ -332,6 +358,8 @@ mod tests {
         plan_data.extend_from_slice(&interval.to_le_bytes());
         plan_data.extend_from_slice(&3600i64.to_le_bytes()); // grace period
         plan_data.extend_from_slice(&0u64.to_le_bytes()); // max_billing_cycles = 0 (infinite)
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
 
         let plan_ix = Instruction {
             program_id: PROGRAM_ID,
@@ -366,7 +394,7 @@ mod tests {
                 AccountMeta::new_readonly(spl_token::ID, false),
                 AccountMeta::new_readonly(system_program::ID, false),
             ],
-            data: get_discriminator("create_subscription").to_vec(),
+            data: create_subscription_data(0),
         };
         svm.send_transaction(Transaction::new_signed_with_payer(
             &[sub_ix],
@@ -395,6 +423,7 @@ mod tests {
                 AccountMeta::new(cranker.pubkey(), true),
                 AccountMeta::new_readonly(service_pda, false),
                 AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
                 AccountMeta::new(subscriber_token, false),
                 AccountMeta::new(treasury, false),
                 AccountMeta::new(cranker_token, false),
@@ -549,6 +578,8 @@ mod tests {
         plan_data.extend_from_slice(&3600i64.to_le_bytes());
         plan_data.extend_from_slice(&3600i64.to_le_bytes());
         plan_data.extend_from_slice(&1u64.to_le_bytes()); // max_billing_cycles = 1 (One-time)
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
 
         let plan_ix = Instruction {
             program_id: PROGRAM_ID,
@@ -583,7 +614,7 @@ mod tests {
                 AccountMeta::new_readonly(spl_token::ID, false),
                 AccountMeta::new_readonly(system_program::ID, false),
             ],
-            data: get_discriminator("create_subscription").to_vec(),
+            data: create_subscription_data(0),
         };
         svm.send_transaction(Transaction::new_signed_with_payer(
             &[sub_ix],
@@ -594,18 +625,12 @@ mod tests {
         .unwrap();
 
         // 4. Verify Completed Status
-        let sub_account_data = svm.get_account(&sub_pda).unwrap().data;
-        // Layout: Disc(8) + subscriber(32) + service(32) + original_plan(32) + plan(32) + token_acct(32) + amount(8) + reward(8) + interval(8) + next(8) + last(8) + created(8) + status(1) + payments(4)
-        // Status at 8+32*5+8*6+1 = 216
-        let status_byte = sub_account_data[216];
-        // 0=Active, 1=PastDue, 2=Cancelled, 3=Expired, 4=Completed
-        assert_eq!(
-            status_byte, 4,
-            "Subscription should be Completed (enum variant 4)"
+        let subscription = get_subscription(&svm, &sub_pda);
+        assert!(
+            subscription.status == SubscriptionStatus::Completed,
+            "Subscription should be Completed"
         );
-
-        let payments_made = u32::from_le_bytes(sub_account_data[217..221].try_into().unwrap());
-        assert_eq!(payments_made, 1, "Should have made 1 payment");
+        assert_eq!(subscription.payments_made, 1, "Should have made 1 payment");
     }
 
     #[test]
@@ -749,6 +774,8 @@ mod tests {
         plan_data.extend_from_slice(&3600i64.to_le_bytes());
         plan_data.extend_from_slice(&3600i64.to_le_bytes());
         plan_data.extend_from_slice(&2u64.to_le_bytes()); // max_billing_cycles = 2
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
 
         let plan_ix = Instruction {
             program_id: PROGRAM_ID,
@@ -783,7 +810,7 @@ mod tests {
                 AccountMeta::new_readonly(spl_token::ID, false),
                 AccountMeta::new_readonly(system_program::ID, false),
             ],
-            data: get_discriminator("create_subscription").to_vec(),
+            data: create_subscription_data(0),
         };
         svm.send_transaction(Transaction::new_signed_with_payer(
             &[sub_ix],
@@ -794,10 +821,9 @@ mod tests {
         .unwrap();
 
         // Verify Active (1/2 paid)
-        let sub_data = svm.get_account(&sub_pda).unwrap().data;
-        assert_eq!(sub_data[216], 0); // Active (Status Offset 216)
-        let payments = u32::from_le_bytes(sub_data[217..221].try_into().unwrap());
-        assert_eq!(payments, 1);
+        let subscription = get_subscription(&svm, &sub_pda);
+        assert!(subscription.status == SubscriptionStatus::Active);
+        assert_eq!(subscription.payments_made, 1);
 
         // Advance Clock
         let mut clock = svm.get_sysvar::<Clock>();
@@ -811,6 +837,7 @@ mod tests {
                 AccountMeta::new(cranker.pubkey(), true),
                 AccountMeta::new_readonly(service_pda, false),
                 AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
                 AccountMeta::new(subscriber_token, false),
                 AccountMeta::new(treasury, false),
                 AccountMeta::new(cranker_token, false),
@@ -828,10 +855,9 @@ mod tests {
         .unwrap();
 
         // Verify Completed (2/2 paid)
-        let sub_data = svm.get_account(&sub_pda).unwrap().data;
-        assert_eq!(sub_data[216], 4); // Completed
-        let payments = u32::from_le_bytes(sub_data[217..221].try_into().unwrap());
-        assert_eq!(payments, 2);
+        let subscription = get_subscription(&svm, &sub_pda);
+        assert!(subscription.status == SubscriptionStatus::Completed);
+        assert_eq!(subscription.payments_made, 2);
     }
 
     #[test]
@@ -974,9 +1000,9 @@ mod tests {
         init_service_and_plan_with_grace(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury, 3600);
         create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
 
-        let mut sub_account = svm.get_account(&sub_pda).unwrap();
-        sub_account.data[216] = 1; // Set status to PastDue
-        svm.set_account(sub_pda, sub_account).unwrap();
+        let mut subscription = get_subscription(&svm, &sub_pda);
+        subscription.status = SubscriptionStatus::PastDue;
+        set_subscription(&mut svm, &sub_pda, &subscription);
 
         let mut clock = svm.get_sysvar::<Clock>();
         clock.unix_timestamp += 7200; // 2 hours past next_billing + grace
@@ -988,9 +1014,12 @@ mod tests {
                 AccountMeta::new(cranker.pubkey(), true),
                 AccountMeta::new_readonly(plan_pda, false),
                 AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber.pubkey(), false),
             ],
             data: get_discriminator("expire_subscription").to_vec(),
         };
+        let sub_rent = svm.get_account(&sub_pda).unwrap().lamports;
+        let subscriber_before = svm.get_account(&subscriber.pubkey()).unwrap().lamports;
         svm.send_transaction(Transaction::new_signed_with_payer(
             &[expire_ix],
             Some(&cranker.pubkey()),
@@ -1000,186 +1029,102 @@ mod tests {
         .expect("Expire subscription failed");
 
         assert!(svm.get_account(&sub_pda).is_none(), "Subscription should be closed");
+        let subscriber_after = svm.get_account(&subscriber.pubkey()).unwrap().lamports;
+        assert_eq!(subscriber_after - subscriber_before, sub_rent, "Rent returns to subscriber");
     }
 
-    fn setup_mint_and_treasury(
-        svm: &mut LiteSVM,
-        merchant: &Keypair,
-        mint: &Pubkey,
-        treasury: &Pubkey,
-    ) {
-        let mut mint_data = vec![0u8; Mint::LEN];
-        Mint::pack(
-            Mint {
-                mint_authority: solana_sdk::program_option::COption::Some(merchant.pubkey()),
-                supply: 100_000_000,
-                decimals: 6,
-                is_initialized: true,
-                freeze_authority: solana_sdk::program_option::COption::None,
-            },
-            &mut mint_data,
-        )
-        .unwrap();
-        svm.set_account(
-            *mint,
-            solana_sdk::account::Account {
-                lamports: 100_000_000,
-                data: mint_data,
-                owner: spl_token::ID,
-                ..Default::default()
-            },
-        )
-        .unwrap();
+    #[test]
+    fn test_revenue_split() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
 
-        let mut treasury_data = vec![0u8; TokenAccount::LEN];
-        TokenAccount::pack(
-            TokenAccount {
-                mint: *mint,
-                owner: merchant.pubkey(),
-                state: spl_token::state::AccountState::Initialized,
-                ..TokenAccount::default()
-            },
-            &mut treasury_data,
-        )
-        .unwrap();
-        svm.set_account(
-            *treasury,
-            solana_sdk::account::Account {
-                lamports: 100_000_000,
-                data: treasury_data,
-                owner: spl_token::ID,
-                ..Default::default()
-            },
-        )
-        .unwrap();
-    }
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let collaborator = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+        let collaborator_token = Pubkey::new_unique();
 
-    fn setup_mint_and_accounts(
-        svm: &mut LiteSVM,
-        merchant: &Keypair,
-        subscriber: &Keypair,
-        mint: &Pubkey,
-        treasury: &Pubkey,
-        subscriber_token: &Pubkey,
-        sub_balance: u64,
-    ) {
-        setup_mint_and_treasury(svm, merchant, mint, treasury);
-        let mut sub_token_data = vec![0u8; TokenAccount::LEN];
-        TokenAccount::pack(
-            TokenAccount {
-                mint: *mint,
-                owner: subscriber.pubkey(),
-                amount: sub_balance,
-                state: spl_token::state::AccountState::Initialized,
-                ..TokenAccount::default()
-            },
-            &mut sub_token_data,
-        )
-        .unwrap();
-        svm.set_account(
-            *subscriber_token,
-            solana_sdk::account::Account {
-                lamports: 100_000_000,
-                data: sub_token_data,
-                owner: spl_token::ID,
-                ..Default::default()
-            },
-        )
-        .unwrap();
-    }
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
 
-    fn init_service_and_plan(
-        svm: &mut LiteSVM,
-        merchant: &Keypair,
-        service_pda: &Pubkey,
-        plan_pda: &Pubkey,
-        mint: &Pubkey,
-        treasury: &Pubkey,
-    ) {
-        init_service_and_plan_with_grace(svm, merchant, service_pda, plan_pda, mint, treasury, 3600);
-    }
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+        setup_token_account(&mut svm, &collaborator_token, &mint, &collaborator.pubkey(), 0);
 
-    fn init_service_and_plan_with_grace(
-        svm: &mut LiteSVM,
-        merchant: &Keypair,
-        service_pda: &Pubkey,
-        plan_pda: &Pubkey,
-        mint: &Pubkey,
-        treasury: &Pubkey,
-        grace_period: i64,
-    ) {
-        let init_ix = Instruction {
-            program_id: PROGRAM_ID,
-            accounts: vec![
-                AccountMeta::new(merchant.pubkey(), true),
-                AccountMeta::new(*service_pda, false),
-                AccountMeta::new_readonly(*mint, false),
-                AccountMeta::new_readonly(*treasury, false),
-                AccountMeta::new_readonly(spl_token::ID, false),
-                AccountMeta::new_readonly(system_program::ID, false),
-            ],
-            data: get_discriminator("initialize_service").to_vec(),
-        };
-        svm.send_transaction(Transaction::new_signed_with_payer(
-            &[init_ix],
-            Some(&merchant.pubkey()),
-            &[&merchant],
-            svm.latest_blockhash(),
-        ))
-        .unwrap();
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (split_pda, _) = get_split_pda(&plan_pda);
 
-        let mut plan_data = get_discriminator("create_plan").to_vec();
-        plan_data.extend_from_slice(&8u32.to_le_bytes());
-        plan_data.extend_from_slice(b"Pro Plan");
-        plan_data.extend_from_slice(&10_000_000u64.to_le_bytes());
-        plan_data.extend_from_slice(&100_000u64.to_le_bytes());
-        plan_data.extend_from_slice(&3600i64.to_le_bytes());
-        plan_data.extend_from_slice(&grace_period.to_le_bytes());
-        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
 
-        let plan_ix = Instruction {
+        // Treasury (primary) keeps 70%, collaborator gets 30%
+        let mut split_data = get_discriminator("create_revenue_split").to_vec();
+        split_data.extend_from_slice(&2u32.to_le_bytes()); // recipients len
+        split_data.extend_from_slice(treasury.as_ref());
+        split_data.extend_from_slice(&7_000u16.to_le_bytes());
+        split_data.extend_from_slice(collaborator_token.as_ref());
+        split_data.extend_from_slice(&3_000u16.to_le_bytes());
+        split_data.push(0); // primary_index
+
+        let split_ix = Instruction {
             program_id: PROGRAM_ID,
             accounts: vec![
                 AccountMeta::new(merchant.pubkey(), true),
-                AccountMeta::new(*service_pda, false),
-                AccountMeta::new(*plan_pda, false),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+                AccountMeta::new(split_pda, false),
                 AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(treasury, false),
+                AccountMeta::new_readonly(collaborator_token, false),
             ],
-            data: plan_data,
+            data: split_data,
         };
         svm.send_transaction(Transaction::new_signed_with_payer(
-            &[plan_ix],
+            &[split_ix],
             Some(&merchant.pubkey()),
             &[&merchant],
             svm.latest_blockhash(),
         ))
-        .unwrap();
-    }
+        .expect("Create revenue split failed");
 
-    fn create_subscription_ix(
-        svm: &mut LiteSVM,
-        subscriber: &Keypair,
-        service_pda: &Pubkey,
-        plan_pda: &Pubkey,
-        sub_pda: &Pubkey,
-        subscriber_token: &Pubkey,
-        mint: &Pubkey,
-        treasury: &Pubkey,
-    ) {
+        // Subscribe — first payment (10 USDC, no reward) is split 7/3
         let sub_ix = Instruction {
             program_id: PROGRAM_ID,
             accounts: vec![
                 AccountMeta::new(subscriber.pubkey(), true),
-                AccountMeta::new(*service_pda, false),
-                AccountMeta::new_readonly(*plan_pda, false),
-                AccountMeta::new(*sub_pda, false),
-                AccountMeta::new(*subscriber_token, false),
-                AccountMeta::new_readonly(*mint, false),
-                AccountMeta::new(*treasury, false),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(treasury, false),
                 AccountMeta::new_readonly(spl_token::ID, false),
                 AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(split_pda, false),
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None price_feed
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None coupon
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None instructions_sysvar
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None affiliate
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None offer_redemption
+                AccountMeta::new(treasury, false),
+                AccountMeta::new(collaborator_token, false),
             ],
-            data: get_discriminator("create_subscription").to_vec(),
+            data: create_subscription_data(0),
         };
         svm.send_transaction(Transaction::new_signed_with_payer(
             &[sub_ix],
@@ -1187,7 +1132,4449 @@ mod tests {
             &[&subscriber],
             svm.latest_blockhash(),
         ))
-        .unwrap();
+        .expect("Create subscription with split failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 7_000_000);
+        let collab_acc =
+            TokenAccount::unpack(&svm.get_account(&collaborator_token).unwrap().data).unwrap();
+        assert_eq!(collab_acc.amount, 3_000_000);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        // Collecting without the split must fail
+        let base_accounts = vec![
+            AccountMeta::new(cranker.pubkey(), true),
+            AccountMeta::new_readonly(service_pda, false),
+            AccountMeta::new(sub_pda, false),
+            AccountMeta::new_readonly(plan_pda, false),
+            AccountMeta::new(subscriber_token, false),
+            AccountMeta::new(treasury, false),
+            AccountMeta::new(cranker_token, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ];
+        let no_split_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: base_accounts.clone(),
+            data: get_discriminator("collect_payment").to_vec(),
+        };
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[no_split_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        // Net of the 0.1 USDC reward (9.9 USDC) is split 7/3
+        let mut accounts = base_accounts;
+        accounts.push(AccountMeta::new_readonly(split_pda, false));
+        accounts.push(AccountMeta::new(treasury, false));
+        accounts.push(AccountMeta::new(collaborator_token, false));
+        let collect_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data: get_discriminator("collect_payment").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment with split failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 7_000_000 + 6_930_000);
+        let collab_acc =
+            TokenAccount::unpack(&svm.get_account(&collaborator_token).unwrap().data).unwrap();
+        assert_eq!(collab_acc.amount, 3_000_000 + 2_970_000);
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 100_000);
+
+        // A batch pays the split too, given the split and its payees
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        let batch = |svm: &mut LiteSVM, with_split: bool| {
+            let approve_ix = spl_token::instruction::approve(
+                &spl_token::ID,
+                &subscriber_token,
+                &sub_pda,
+                &subscriber.pubkey(),
+                &[],
+                10_000_000,
+            )
+            .unwrap();
+            let mut tuple = vec![
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new(treasury, false),
+            ];
+            if with_split {
+                tuple.extend([
+                    AccountMeta::new_readonly(split_pda, false),
+                    AccountMeta::new(treasury, false),
+                    AccountMeta::new(collaborator_token, false),
+                ]);
+            }
+            let batch_ix = collect_payments_batch_ix(
+                &cranker,
+                &service_pda,
+                &cranker_token,
+                &mint,
+                &spl_token::ID,
+                &[tuple],
+            );
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[approve_ix, batch_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker, &subscriber],
+                svm.latest_blockhash(),
+            ))
+        };
+        assert!(batch(&mut svm, false).is_err());
+        batch(&mut svm, true).expect("Batch collection with split failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 7_000_000 + 2 * 6_930_000);
+        let collab_acc =
+            TokenAccount::unpack(&svm.get_account(&collaborator_token).unwrap().data).unwrap();
+        assert_eq!(collab_acc.amount, 3_000_000 + 2 * 2_970_000);
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 200_000);
+    }
+
+    #[test]
+    fn test_percentage_crank_reward() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 2% of 10 USDC = 0.2 USDC, clamped to a 0.15 USDC ceiling
+        let mut update_data = get_discriminator("set_reward_policy").to_vec();
+        update_data.push(1); // Some percentage_reward
+        update_data.extend_from_slice(&200u16.to_le_bytes()); // bps
+        update_data.extend_from_slice(&50_000u64.to_le_bytes()); // floor
+        update_data.extend_from_slice(&150_000u64.to_le_bytes()); // ceiling
+        update_data.push(0); // None reward_escalation
+
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[update_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set reward policy failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 150_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 10_000_000 + 9_850_000);
+    }
+
+    #[test]
+    fn test_escalating_crank_reward() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // +0.05 USDC per 10 minutes overdue, capped at 0.4 USDC
+        let mut update_data = get_discriminator("set_reward_policy").to_vec();
+        update_data.push(0); // None percentage_reward
+        update_data.push(1); // Some reward_escalation
+        update_data.extend_from_slice(&50_000u64.to_le_bytes()); // step_amount
+        update_data.extend_from_slice(&600i64.to_le_bytes()); // step_interval
+        update_data.extend_from_slice(&400_000u64.to_le_bytes()); // max_reward
+
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[update_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set reward policy failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        // 30 minutes overdue = 3 steps on top of the 0.1 USDC base reward
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3600 + 1800;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 100_000 + 3 * 50_000);
+    }
+
+    #[test]
+    fn test_reward_pool() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), 2 * LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (pool_pda, _) = get_reward_pool_pda(&service_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        let lamports_per_crank: u64 = 1_000_000;
+        let mut create_data = get_discriminator("create_reward_pool").to_vec();
+        create_data.extend_from_slice(&lamports_per_crank.to_le_bytes());
+        create_data.extend_from_slice(&0u64.to_le_bytes()); // maintenance_reward
+        let create_pool_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(pool_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: create_data,
+        };
+        let mut deposit_data = get_discriminator("deposit_reward_pool").to_vec();
+        deposit_data.extend_from_slice(&(LAMPORTS_PER_SOL / 2).to_le_bytes());
+        let deposit_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(pool_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: deposit_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[create_pool_ix, deposit_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create and fund reward pool failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let pool_before = svm.get_account(&pool_pda).unwrap().lamports;
+
+        let mut collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        collect_ix.accounts.push(AccountMeta::new_readonly(PROGRAM_ID, false)); // No revenue split
+        collect_ix.accounts.push(AccountMeta::new(pool_pda, false));
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        // Reward came from the pool, so the full payment reached the treasury
+        let pool_after = svm.get_account(&pool_pda).unwrap().lamports;
+        assert_eq!(pool_before - pool_after, lamports_per_crank);
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 0);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 2 * 10_000_000);
+    }
+
+    #[test]
+    fn test_mark_past_due_with_maintenance_reward() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), 2 * LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        // Only enough for the upfront payment
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            10_000_000,
+        );
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (pool_pda, _) = get_reward_pool_pda(&service_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        let maintenance_reward: u64 = 200_000;
+        let mut create_data = get_discriminator("create_reward_pool").to_vec();
+        create_data.extend_from_slice(&1_000_000u64.to_le_bytes()); // lamports_per_crank
+        create_data.extend_from_slice(&maintenance_reward.to_le_bytes());
+        let create_pool_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(pool_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: create_data,
+        };
+        let mut deposit_data = get_discriminator("deposit_reward_pool").to_vec();
+        deposit_data.extend_from_slice(&(LAMPORTS_PER_SOL / 2).to_le_bytes());
+        let deposit_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(pool_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: deposit_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[create_pool_ix, deposit_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create and fund reward pool failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        // Subscriber balance is now 0, so the charge cannot be collected
+        let mark_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(subscriber_token, false),
+                AccountMeta::new(pool_pda, false),
+            ],
+            data: get_discriminator("mark_past_due").to_vec(),
+        };
+        let pool_before = svm.get_account(&pool_pda).unwrap().lamports;
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[mark_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Mark past due failed");
+
+        assert!(get_subscription(&svm, &sub_pda).status == SubscriptionStatus::PastDue);
+        let pool_after = svm.get_account(&pool_pda).unwrap().lamports;
+        assert_eq!(pool_before - pool_after, maintenance_reward);
+
+        // Expire after grace: rent goes to the subscriber, reward to the cranker
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3600;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let expire_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber.pubkey(), false),
+                AccountMeta::new(pool_pda, false),
+            ],
+            data: get_discriminator("expire_subscription").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[expire_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Expire subscription failed");
+
+        assert!(svm.get_account(&sub_pda).is_none(), "Subscription should be closed");
+        let pool_final = svm.get_account(&pool_pda).unwrap().lamports;
+        assert_eq!(pool_after - pool_final, maintenance_reward);
+    }
+
+    #[test]
+    fn test_exclusive_collection_window() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 30 minute merchant-only window after each due date
+        let mut update_data = get_discriminator("set_exclusive_window").to_vec();
+        update_data.extend_from_slice(&1800i64.to_le_bytes());
+
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[update_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set exclusive window failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        // A public cranker is locked out during the window
+        let public_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[public_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        // The merchant collects into its own treasury, with no reward carved out
+        let merchant_ix = collect_payment_ix(
+            &merchant,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &treasury,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[merchant_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Merchant collection failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 2 * 10_000_000);
+    }
+
+    #[test]
+    fn test_collect_payments_batch() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let funded = Keypair::new();
+        let broke = Keypair::new();
+        let frozen = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let funded_token = Pubkey::new_unique();
+        let broke_token = Pubkey::new_unique();
+        let frozen_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&funded.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&broke.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&frozen.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &funded,
+            &mint,
+            &treasury,
+            &funded_token,
+            50_000_000,
+        );
+        // Only enough for the upfront payment
+        setup_token_account(&mut svm, &broke_token, &mint, &broke.pubkey(), 10_000_000);
+        setup_token_account(&mut svm, &frozen_token, &mint, &frozen.pubkey(), 50_000_000);
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (funded_sub, _) = get_subscription_pda(&funded.pubkey(), &plan_pda);
+        let (broke_sub, _) = get_subscription_pda(&broke.pubkey(), &plan_pda);
+        let (frozen_sub, _) = get_subscription_pda(&frozen.pubkey(), &plan_pda);
+        // Stands in for a subscription closed after the batch was assembled
+        let (closed_sub, _) = get_subscription_pda(&Pubkey::new_unique(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+        create_subscription_ix(&mut svm, &funded, &service_pda, &plan_pda, &funded_sub, &funded_token, &mint, &treasury);
+        create_subscription_ix(&mut svm, &broke, &service_pda, &plan_pda, &broke_sub, &broke_token, &mint, &treasury);
+        create_subscription_ix(&mut svm, &frozen, &service_pda, &plan_pda, &frozen_sub, &frozen_token, &mint, &treasury);
+
+        // Frozen by the mint's freeze authority after subscribing
+        let mut frozen_acc = svm.get_account(&frozen_token).unwrap();
+        let mut frozen_state = TokenAccount::unpack(&frozen_acc.data).unwrap();
+        frozen_state.state = spl_token::state::AccountState::Frozen;
+        TokenAccount::pack(frozen_state, &mut frozen_acc.data).unwrap();
+        svm.set_account(frozen_token, frozen_acc).unwrap();
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let (reward_vault, _) = Pubkey::find_program_address(
+            &[b"batch_rewards", cranker.pubkey().as_ref()],
+            &PROGRAM_ID,
+        );
+        let tuple = |sub: &Pubkey, token: &Pubkey| {
+            vec![
+                AccountMeta::new(*sub, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(*token, false),
+                AccountMeta::new(treasury, false),
+            ]
+        };
+        let batch_ix = collect_payments_batch_ix(
+            &cranker,
+            &service_pda,
+            &cranker_token,
+            &mint,
+            &spl_token::ID,
+            &[
+                tuple(&funded_sub, &funded_token),
+                tuple(&broke_sub, &broke_token),
+                tuple(&frozen_sub, &frozen_token),
+                tuple(&closed_sub, &broke_token),
+            ],
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[batch_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Batch collection failed");
+
+        // The funded subscription was collected, the broke, frozen and closed ones skipped
+        assert_eq!(get_subscription(&svm, &funded_sub).payments_made, 2);
+        assert_eq!(get_subscription(&svm, &broke_sub).payments_made, 1);
+        assert_eq!(get_subscription(&svm, &frozen_sub).payments_made, 1);
+
+        // The merchant's share went straight to the treasury, and the reward
+        // vault was emptied into the cranker's account and closed
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 100_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 3 * 10_000_000 + 9_900_000);
+        assert!(svm
+            .get_account(&reward_vault)
+            .is_none_or(|vault| vault.lamports == 0));
+    }
+
+    #[test]
+    fn test_native_sol_subscription() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let sol_treasury = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&sol_treasury.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_treasury(&mut svm, &merchant, &mint, &treasury);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (spl_plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (plan_pda, _) = get_plan_pda(&service_pda, 1);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (escrow_pda, _) =
+            Pubkey::find_program_address(&[b"escrow", sub_pda.as_ref()], &PROGRAM_ID);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &spl_plan_pda, &mint, &treasury);
+
+        // Enable native billing and create a 0.1 SOL/hour plan
+        let mut treasury_data = get_discriminator("set_sol_treasury").to_vec();
+        treasury_data.push(1);
+        treasury_data.extend_from_slice(sol_treasury.pubkey().as_ref());
+
+        let price = LAMPORTS_PER_SOL / 10;
+        let crank_reward = 10_000u64;
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&7u32.to_le_bytes());
+        plan_data.extend_from_slice(b"SOL Pro");
+        plan_data.extend_from_slice(&price.to_le_bytes());
+        plan_data.extend_from_slice(&crank_reward.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(1); // native_sol true
+
+        let setup_ixs = vec![
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                ],
+                data: treasury_data,
+            },
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: plan_data,
+            },
+        ];
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &setup_ixs,
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Native plan setup failed");
+
+        // Subscribe, paying the first period and pre-funding two more
+        let mut sub_data = get_discriminator("create_native_subscription").to_vec();
+        sub_data.extend_from_slice(&(2 * price).to_le_bytes());
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new(sol_treasury.pubkey(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: sub_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Native subscription failed");
+
+        assert_eq!(
+            svm.get_account(&sol_treasury.pubkey()).unwrap().lamports,
+            LAMPORTS_PER_SOL + price
+        );
+        assert_eq!(svm.get_account(&escrow_pda).unwrap().lamports, 2 * price);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let collect_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new(sol_treasury.pubkey(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: get_discriminator("collect_native_payment").to_vec(),
+        };
+        let cranker_before = svm.get_account(&cranker.pubkey()).unwrap().lamports;
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Native collection failed");
+
+        assert_eq!(
+            svm.get_account(&sol_treasury.pubkey()).unwrap().lamports,
+            LAMPORTS_PER_SOL + 2 * price - crank_reward
+        );
+        assert_eq!(svm.get_account(&escrow_pda).unwrap().lamports, price);
+        // Reward minus the 5000 lamport signature fee
+        assert_eq!(
+            svm.get_account(&cranker.pubkey()).unwrap().lamports,
+            cranker_before + crank_reward - 5000
+        );
+
+        // Cancelling refunds the remaining escrow to the subscriber
+        let cancel_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: get_discriminator("cancel_native_subscription").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[cancel_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Native cancel failed");
+
+        assert!(svm.get_account(&sub_pda).is_none());
+        assert_eq!(
+            svm.get_account(&escrow_pda).map_or(0, |acc| acc.lamports),
+            0
+        );
+    }
+
+    #[test]
+    fn test_plan_currency_override() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let usdc = Pubkey::new_unique();
+        let usdc_treasury = Pubkey::new_unique();
+        let pyusd = Pubkey::new_unique();
+        let pyusd_treasury = Pubkey::new_unique();
+        let subscriber_pyusd = Pubkey::new_unique();
+        let cranker_pyusd = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_treasury(&mut svm, &merchant, &usdc, &usdc_treasury);
+        setup_mint_and_treasury(&mut svm, &merchant, &pyusd, &pyusd_treasury);
+        setup_token_account(&mut svm, &subscriber_pyusd, &pyusd, &subscriber.pubkey(), 50_000_000);
+        setup_token_account(&mut svm, &cranker_pyusd, &pyusd, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &usdc, &usdc_treasury);
+
+        // Price the plan in PYUSD instead of the service's USDC
+        let currency_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+                AccountMeta::new_readonly(pyusd, false),
+                AccountMeta::new_readonly(pyusd_treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: get_discriminator("set_plan_currency").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[currency_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set plan currency failed");
+
+        create_subscription_ix(
+            &mut svm,
+            &subscriber,
+            &service_pda,
+            &plan_pda,
+            &sub_pda,
+            &subscriber_pyusd,
+            &pyusd,
+            &pyusd_treasury,
+        );
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        // The service's own treasury is rejected for this plan
+        let wrong_treasury_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_pyusd,
+            &usdc_treasury,
+            &cranker_pyusd,
+            &pyusd,
+        );
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[wrong_treasury_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_pyusd,
+            &pyusd_treasury,
+            &cranker_pyusd,
+            &pyusd,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collection in plan mint failed");
+
+        let treasury_acc =
+            TokenAccount::unpack(&svm.get_account(&pyusd_treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 2 * 10_000_000 - 100_000);
+        let usdc_acc =
+            TokenAccount::unpack(&svm.get_account(&usdc_treasury).unwrap().data).unwrap();
+        assert_eq!(usdc_acc.amount, 0);
+    }
+
+    #[test]
+    fn test_token_2022_transfer_fee() {
+        use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
+
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        // 1% fee on every transfer
+        setup_fee_mint(&mut svm, &mint, &merchant.pubkey(), 100);
+        for (address, owner, amount) in [
+            (treasury, merchant.pubkey(), 0),
+            (subscriber_token, subscriber.pubkey(), 50_000_000),
+            (cranker_token, cranker.pubkey(), 0),
+        ] {
+            setup_token_2022_account(
+                &mut svm,
+                &address,
+                &mint,
+                &owner,
+                amount,
+                ExtensionType::TransferFeeAmount,
+            );
+        }
+
+        let token_2022 = anchor_spl::token_2022::ID;
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&8u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Fee Plan");
+        plan_data.extend_from_slice(&10_000_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&100_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+
+        let mut update_data = get_discriminator("set_transfer_fee_mode").to_vec();
+        update_data.push(1); // SubscriberPays
+
+        let setup_ixs = vec![
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(treasury, false),
+                    AccountMeta::new_readonly(token_2022, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: get_discriminator("initialize_service").to_vec(),
+            },
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: plan_data,
+            },
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(merchant.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                ],
+                data: update_data,
+            },
+        ];
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &setup_ixs,
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Fee plan setup failed");
+
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new_readonly(token_2022, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: create_subscription_data(0),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Subscription failed");
+
+        // The treasury nets the full price; the subscriber pays the fee on top
+        assert_eq!(token_2022_balance(&svm, &treasury), 10_000_000);
+        assert_eq!(
+            token_2022_balance(&svm, &subscriber_token),
+            50_000_000 - 10_101_011
+        );
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let mut collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        collect_ix.accounts[8] = AccountMeta::new_readonly(token_2022, false);
+
+        // Caps count the fee the subscriber pays on top of the price
+        let set_per_charge_cap = |svm: &mut LiteSVM, per_charge: u64| {
+            let mut caps_data = get_discriminator("set_spending_caps").to_vec();
+            caps_data.extend_from_slice(&per_charge.to_le_bytes());
+            caps_data.extend_from_slice(&[0; 24]); // no period or lifetime caps
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[Instruction {
+                    program_id: PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new_readonly(subscriber.pubkey(), true),
+                        AccountMeta::new(sub_pda, false),
+                    ],
+                    data: caps_data,
+                }],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+            .expect("Set spending caps failed");
+        };
+        set_per_charge_cap(&mut svm, 10_000_000);
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                std::slice::from_ref(&collect_ix),
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        set_per_charge_cap(&mut svm, 10_101_011);
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collection failed");
+
+        // Both the reward and the treasury share arrive in full
+        assert_eq!(token_2022_balance(&svm, &cranker_token), 100_000);
+        assert_eq!(
+            token_2022_balance(&svm, &treasury),
+            10_000_000 + 10_000_000 - 100_000
+        );
+    }
+
+    #[test]
+    fn test_fee_mint_discounted_crank_reward() {
+        use crate::state::{AppliedDiscount, Discount};
+        use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
+
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        // 1% fee on every transfer, absorbed by the merchant
+        setup_fee_mint(&mut svm, &mint, &merchant.pubkey(), 100);
+        for (address, owner, amount) in [
+            (treasury, merchant.pubkey(), 0),
+            (subscriber_token, subscriber.pubkey(), 50_000_000),
+            (cranker_token, cranker.pubkey(), 0),
+        ] {
+            setup_token_2022_account(
+                &mut svm,
+                &address,
+                &mint,
+                &owner,
+                amount,
+                ExtensionType::TransferFeeAmount,
+            );
+        }
+
+        let token_2022 = anchor_spl::token_2022::ID;
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        // A 9.95 USDC reward fits the 10 USDC price, but not what it nets after the fee
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&8u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Fee Plan");
+        plan_data.extend_from_slice(&10_000_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&9_950_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+
+        let setup_ixs = vec![
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(treasury, false),
+                    AccountMeta::new_readonly(token_2022, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: get_discriminator("initialize_service").to_vec(),
+            },
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: plan_data,
+            },
+        ];
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &setup_ixs,
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Fee plan setup failed");
+
+        let subscribe = |svm: &mut LiteSVM| {
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[Instruction {
+                    program_id: PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new(subscriber.pubkey(), true),
+                        AccountMeta::new(service_pda, false),
+                        AccountMeta::new_readonly(plan_pda, false),
+                        AccountMeta::new(sub_pda, false),
+                        AccountMeta::new(subscriber_token, false),
+                        AccountMeta::new_readonly(mint, false),
+                        AccountMeta::new(treasury, false),
+                        AccountMeta::new_readonly(token_2022, false),
+                        AccountMeta::new_readonly(system_program::ID, false),
+                    ],
+                    data: create_subscription_data(0),
+                }],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+        };
+        assert!(subscribe(&mut svm).is_err());
+
+        let mut update_data = get_discriminator("update_plan").to_vec();
+        update_data.push(0); // None amount
+        update_data.push(1); // Some cranker_reward
+        update_data.extend_from_slice(&100_000u64.to_le_bytes());
+        update_data.push(0); // None interval
+        update_data.push(0); // None is_active
+        update_data.push(0); // None grace_period
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                ],
+                data: update_data,
+            }],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Update plan failed");
+        subscribe(&mut svm).expect("Subscription failed");
+
+        // A 99.5% discount leaves a 0.05 USDC charge, less than the 0.1 USDC
+        // reward grossed up for the fee
+        let mut subscription = get_subscription(&svm, &sub_pda);
+        subscription.discount = Some(AppliedDiscount {
+            coupon: Pubkey::new_unique(),
+            discount: Discount::PercentOff(9_950),
+            remaining_cycles: None,
+        });
+        set_subscription(&mut svm, &sub_pda, &subscription);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let mut collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        collect_ix.accounts[8] = AccountMeta::new_readonly(token_2022, false);
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collection failed");
+
+        // The reward is cut to what the charge nets after the fee, and
+        // nothing is left for the treasury
+        assert_eq!(get_subscription(&svm, &sub_pda).payments_made, 2);
+        assert_eq!(token_2022_balance(&svm, &cranker_token), 49_500);
+        assert_eq!(token_2022_balance(&svm, &treasury), 9_900_000);
+    }
+
+    #[test]
+    fn test_transfer_hook_payments() {
+        use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
+        use spl_tlv_account_resolution::{account::ExtraAccountMeta, state::ExtraAccountMetaList};
+        use spl_transfer_hook_interface::{
+            get_extra_account_metas_address, instruction::ExecuteInstruction,
+        };
+
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+        let hook_program = address_to_pubkey("6Bv9XZ6GUmtDDhC7KqXzhdjgH9d33CQKvf2MNtn6K99v");
+        let hook_bytes = include_bytes!("../../../target/deploy/mock_transfer_hook.so");
+        let _ = svm.add_program(hook_program, hook_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_hook_mint(&mut svm, &mint, &merchant.pubkey(), &hook_program);
+        for (address, owner, amount) in [
+            (treasury, merchant.pubkey(), 0),
+            (subscriber_token, subscriber.pubkey(), 50_000_000),
+            (cranker_token, cranker.pubkey(), 0),
+        ] {
+            setup_token_2022_account(
+                &mut svm,
+                &address,
+                &mint,
+                &owner,
+                amount,
+                ExtensionType::TransferHookAccount,
+            );
+        }
+
+        // The hook requires one extra account: a transfer counter it owns
+        let validation = get_extra_account_metas_address(&mint, &hook_program);
+        let metas = [ExtraAccountMeta::new_with_pubkey(&counter, false, true).unwrap()];
+        let mut validation_data = vec![0u8; ExtraAccountMetaList::size_of(metas.len()).unwrap()];
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut validation_data, &metas).unwrap();
+        for (address, data) in [(validation, validation_data), (counter, vec![0u8; 8])] {
+            svm.set_account(
+                address,
+                solana_sdk::account::Account {
+                    lamports: 10_000_000,
+                    data,
+                    owner: hook_program,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        let hook_accounts = [
+            AccountMeta::new_readonly(hook_program, false),
+            AccountMeta::new_readonly(validation, false),
+            AccountMeta::new(counter, false),
+        ];
+        let transfers = |svm: &LiteSVM| {
+            u64::from_le_bytes(svm.get_account(&counter).unwrap().data[..8].try_into().unwrap())
+        };
+
+        let token_2022 = anchor_spl::token_2022::ID;
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&9u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Hook Plan");
+        plan_data.extend_from_slice(&10_000_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&100_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+
+        let setup_ixs = vec![
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(treasury, false),
+                    AccountMeta::new_readonly(token_2022, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: get_discriminator("initialize_service").to_vec(),
+            },
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(merchant.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data: plan_data,
+            },
+        ];
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &setup_ixs,
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Hook plan setup failed");
+
+        // No revenue split or other optional accounts: the program ID stands
+        // in for each, and the hook accounts follow as remaining accounts
+        let mut sub_accounts = vec![
+            AccountMeta::new(subscriber.pubkey(), true),
+            AccountMeta::new(service_pda, false),
+            AccountMeta::new_readonly(plan_pda, false),
+            AccountMeta::new(sub_pda, false),
+            AccountMeta::new(subscriber_token, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(treasury, false),
+            AccountMeta::new_readonly(token_2022, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ];
+        sub_accounts.extend(vec![AccountMeta::new_readonly(PROGRAM_ID, false); 6]);
+        sub_accounts.extend_from_slice(&hook_accounts);
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: sub_accounts,
+            data: create_subscription_data(0),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Subscription with transfer hook failed");
+        assert_eq!(transfers(&svm), 1);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let mut collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        collect_ix.accounts[8] = AccountMeta::new_readonly(token_2022, false);
+        collect_ix.accounts.push(AccountMeta::new_readonly(PROGRAM_ID, false)); // revenue_split
+        collect_ix.accounts.push(AccountMeta::new_readonly(PROGRAM_ID, false)); // reward_pool
+        collect_ix.accounts.extend_from_slice(&hook_accounts);
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collection with transfer hook failed");
+
+        // Cranker reward and treasury share each ran the hook
+        assert_eq!(transfers(&svm), 3);
+        assert_eq!(token_2022_balance(&svm, &cranker_token), 100_000);
+
+        // A batch passes the hook accounts after its tuples. The treasury
+        // share, the reward into the vault and its payout each run the hook
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        let approve_ix = anchor_spl::token_2022::spl_token_2022::instruction::approve(
+            &token_2022,
+            &subscriber_token,
+            &sub_pda,
+            &subscriber.pubkey(),
+            &[],
+            10_000_000,
+        )
+        .unwrap();
+        let mut batch_ix = collect_payments_batch_ix(
+            &cranker,
+            &service_pda,
+            &cranker_token,
+            &mint,
+            &token_2022,
+            &[vec![
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new(treasury, false),
+            ]],
+        );
+        batch_ix.accounts.extend_from_slice(&hook_accounts);
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix, batch_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Batch collection with transfer hook failed");
+
+        assert_eq!(transfers(&svm), 6);
+        assert_eq!(token_2022_balance(&svm, &cranker_token), 200_000);
+    }
+
+    #[test]
+    fn test_quote_priced_plan() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+        let price_feed = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        // Plan: 10 USD per interval with a 0.10 USD crank reward
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // Token trades at 2.00 USD
+        let now = svm.get_sysvar::<Clock>().unix_timestamp;
+        set_price_feed(&mut svm, &price_feed, 200_000_000, -8, now);
+
+        let mut pricing_data = get_discriminator("set_quote_pricing").to_vec();
+        pricing_data.push(1); // Some pricing
+        pricing_data.push(0); // PriceSource::Pyth
+        pricing_data.extend_from_slice(price_feed.as_ref());
+        pricing_data.extend_from_slice(&60i64.to_le_bytes()); // max_staleness
+        pricing_data.extend_from_slice(&100u16.to_le_bytes()); // max_confidence_bps
+        let pricing_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+                AccountMeta::new_readonly(price_feed, false),
+            ],
+            data: pricing_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[pricing_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set quote pricing failed");
+
+        // Subscribe, accepting at most 8 tokens per charge
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None revenue_split
+                AccountMeta::new_readonly(price_feed, false),
+            ],
+            data: create_subscription_data(8_000_000),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create subscription failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 5_000_000);
+        let sub_token_acc =
+            TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_token_acc.delegated_amount, 8_000_000);
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let collect = |svm: &mut LiteSVM| {
+            let mut ix = collect_payment_ix(
+                &cranker,
+                &service_pda,
+                &sub_pda,
+                &plan_pda,
+                &subscriber_token,
+                &treasury,
+                &cranker_token,
+                &mint,
+            );
+            ix.accounts.push(AccountMeta::new_readonly(PROGRAM_ID, false)); // None revenue_split
+            ix.accounts.push(AccountMeta::new_readonly(PROGRAM_ID, false)); // None reward_pool
+            ix.accounts.push(AccountMeta::new_readonly(price_feed, false));
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        // A stale price is refused
+        assert!(collect(&mut svm).is_err());
+
+        // At 1.00 USD the 10 tokens owed exceed the subscriber's cap
+        set_price_feed(&mut svm, &price_feed, 100_000_000, -8, clock.unix_timestamp);
+        assert!(collect(&mut svm).is_err());
+
+        // At 1.60 USD the charge is 6.25 tokens, 0.0625 of which go to the cranker
+        set_price_feed(&mut svm, &price_feed, 160_000_000, -8, clock.unix_timestamp);
+        collect(&mut svm).expect("Collect payment failed");
+
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 62_500);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 5_000_000 + 6_187_500);
+
+        // A batch prices the next cycle from the feed passed after its tuple:
+        // at 2.00 USD the charge is 5 tokens, 0.05 of which go to the cranker
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        set_price_feed(&mut svm, &price_feed, 200_000_000, -8, clock.unix_timestamp);
+        let approve_ix = spl_token::instruction::approve(
+            &spl_token::ID,
+            &subscriber_token,
+            &sub_pda,
+            &subscriber.pubkey(),
+            &[],
+            8_000_000,
+        )
+        .unwrap();
+        let batch_ix = collect_payments_batch_ix(
+            &cranker,
+            &service_pda,
+            &cranker_token,
+            &mint,
+            &spl_token::ID,
+            &[vec![
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new_readonly(price_feed, false),
+            ]],
+        );
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix, batch_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Batch collection with quote pricing failed");
+
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 62_500 + 50_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 5_000_000 + 6_187_500 + 4_950_000);
+    }
+
+    #[test]
+    fn test_spending_caps() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            100_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        // 10 USDC hourly, capped at 15 USDC per two hours and 20 USDC in total
+        let mut caps_data = get_discriminator("set_spending_caps").to_vec();
+        caps_data.extend_from_slice(&20_000_000u64.to_le_bytes()); // per_charge
+        caps_data.extend_from_slice(&15_000_000u64.to_le_bytes()); // per_period
+        caps_data.extend_from_slice(&7200i64.to_le_bytes()); // period
+        caps_data.extend_from_slice(&20_000_000u64.to_le_bytes()); // lifetime
+        let caps_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+            ],
+            data: caps_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[caps_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set spending caps failed");
+
+        let collect = |svm: &mut LiteSVM, elapsed: i64| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += elapsed;
+            svm.set_sysvar::<Clock>(&clock);
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[collect_payment_ix(
+                    &cranker,
+                    &service_pda,
+                    &sub_pda,
+                    &plan_pda,
+                    &subscriber_token,
+                    &treasury,
+                    &cranker_token,
+                    &mint,
+                )],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        // Second charge inside the first period would reach 20 USDC
+        assert!(collect(&mut svm, 3601).is_err());
+
+        // Once the period rolls over the charge fits
+        collect(&mut svm, 3600).expect("Collect payment failed");
+
+        // A third charge would exceed the lifetime cap
+        assert!(collect(&mut svm, 7200).is_err());
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 10_000_000 + 9_900_000);
+    }
+
+    #[test]
+    fn test_setup_fee_and_one_off_charge() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 5 USDC activation fee
+        let mut update_data = get_discriminator("set_setup_fee").to_vec();
+        update_data.extend_from_slice(&5_000_000u64.to_le_bytes());
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[update_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set setup fee failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 15_000_000);
+
+        // 2 USDC overage, billed with the next period
+        let mut charge_data = get_discriminator("add_one_off_charge").to_vec();
+        charge_data.extend_from_slice(&2_000_000u64.to_le_bytes());
+        let charge_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(subscriber_token, false),
+            ],
+            data: charge_data,
+        };
+
+        // The delegation only covers the recurring 10 USDC
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                std::slice::from_ref(&charge_ix),
+                Some(&merchant.pubkey()),
+                &[&merchant],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        let approve_ix = spl_token::instruction::approve(
+            &spl_token::ID,
+            &subscriber_token,
+            &sub_pda,
+            &subscriber.pubkey(),
+            &[],
+            12_000_000,
+        )
+        .unwrap();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Approve failed");
+
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[charge_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Add one-off charge failed");
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let collect = |svm: &mut LiteSVM| {
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[collect_payment_ix(
+                    &cranker,
+                    &service_pda,
+                    &sub_pda,
+                    &plan_pda,
+                    &subscriber_token,
+                    &treasury,
+                    &cranker_token,
+                    &mint,
+                )],
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        collect(&mut svm).expect("Collect payment failed");
+
+        let cranker_acc =
+            TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(cranker_acc.amount, 100_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 15_000_000 + 11_900_000);
+    }
+
+    #[test]
+    fn test_late_fee() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            25_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 10% late fee, capped at 0.5 USDC
+        let mut update_data = get_discriminator("set_late_fee").to_vec();
+        update_data.push(1); // Some late_fee
+        update_data.push(1); // LateFeeKind::Bps
+        update_data.extend_from_slice(&1_000u16.to_le_bytes());
+        update_data.extend_from_slice(&500_000u64.to_le_bytes()); // max_fee
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[update_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set late fee failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        // The subscriber revokes the delegation, so the next charge is missed
+        let revoke_ix = spl_token::instruction::revoke(
+            &spl_token::ID,
+            &subscriber_token,
+            &subscriber.pubkey(),
+            &[],
+        )
+        .unwrap();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[revoke_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Revoke failed");
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+
+        let past_due_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(subscriber_token, false),
+            ],
+            data: get_discriminator("mark_past_due").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            std::slice::from_ref(&past_due_ix),
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Mark past due failed");
+
+        // Re-approving covers the charge plus the capped late fee
+        let approve_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: get_discriminator("reapprove_delegation").to_vec(),
+        };
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix.clone(), collect_ix.clone()],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let sub_token_acc =
+            TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_token_acc.amount, 25_000_000 - 10_000_000 - 10_500_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 10_000_000 + 10_400_000);
+
+        // An approval made while active already covers the capped late fee
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Reapprove failed");
+        let sub_token_acc =
+            TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_token_acc.delegated_amount, 10_500_000);
+
+        // The next charge is missed for lack of funds
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[past_due_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Mark past due failed");
+
+        // Topping up is enough to recover: the charge and its late fee are
+        // collected without approving again
+        let mut token_acc = svm.get_account(&subscriber_token).unwrap();
+        let mut token_state = TokenAccount::unpack(&token_acc.data).unwrap();
+        token_state.amount += 10_000_000;
+        TokenAccount::pack(token_state, &mut token_acc.data).unwrap();
+        svm.set_account(subscriber_token, token_acc).unwrap();
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment after top-up failed");
+
+        let sub_token_acc =
+            TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_token_acc.amount, 4_500_000 + 10_000_000 - 10_500_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 10_000_000 + 2 * 10_400_000);
+        assert!(get_subscription(&svm, &sub_pda).status == SubscriptionStatus::Active);
+    }
+
+    #[test]
+    fn test_coupon_redemption() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let other_subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let other_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&other_subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &other_token, &mint, &other_subscriber.pubkey(), 50_000_000);
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (other_sub_pda, _) = get_subscription_pda(&other_subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // LAUNCH50: 50% off the first two charges, redeemable once, on this plan only
+        let mut code = [0u8; 32];
+        code[..8].copy_from_slice(b"LAUNCH50");
+        let (coupon_pda, _) =
+            Pubkey::find_program_address(&[b"coupon", service_pda.as_ref(), &code], &PROGRAM_ID);
+        let mut coupon_data = get_discriminator("create_coupon").to_vec();
+        coupon_data.extend_from_slice(&code);
+        coupon_data.push(0); // Discount::PercentOff
+        coupon_data.extend_from_slice(&5_000u16.to_le_bytes());
+        coupon_data.push(1); // CouponDuration::Cycles
+        coupon_data.extend_from_slice(&2u32.to_le_bytes());
+        coupon_data.extend_from_slice(&1u32.to_le_bytes()); // max_redemptions
+        coupon_data.extend_from_slice(&0i64.to_le_bytes()); // never expires
+        coupon_data.extend_from_slice(&1u32.to_le_bytes()); // eligible_plans
+        coupon_data.extend_from_slice(plan_pda.as_ref());
+        let coupon_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(coupon_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: coupon_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[coupon_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create coupon failed");
+
+        let subscribe_ix = |subscriber: &Keypair, sub_pda: &Pubkey, token: &Pubkey| Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(*sub_pda, false),
+                AccountMeta::new(*token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None revenue_split
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None price_feed
+                AccountMeta::new(coupon_pda, false),
+            ],
+            data: create_subscription_data(0),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[subscribe_ix(&subscriber, &sub_pda, &subscriber_token)],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create subscription failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 5_000_000);
+
+        // The single redemption is used up
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[subscribe_ix(&other_subscriber, &other_sub_pda, &other_token)],
+                Some(&other_subscriber.pubkey()),
+                &[&other_subscriber],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        let collect = |svm: &mut LiteSVM| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += 3601;
+            svm.set_sysvar::<Clock>(&clock);
+            let approve_ix = spl_token::instruction::approve(
+                &spl_token::ID,
+                &subscriber_token,
+                &sub_pda,
+                &subscriber.pubkey(),
+                &[],
+                10_000_000,
+            )
+            .unwrap();
+            let collect_ix = collect_payment_ix(
+                &cranker,
+                &service_pda,
+                &sub_pda,
+                &plan_pda,
+                &subscriber_token,
+                &treasury,
+                &cranker_token,
+                &mint,
+            );
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[approve_ix, collect_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker, &subscriber],
+                svm.latest_blockhash(),
+            ))
+            .expect("Collect payment failed");
+        };
+
+        // Second discounted charge, then full price once the coupon runs out
+        collect(&mut svm);
+        collect(&mut svm);
+
+        let sub_token_acc =
+            TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_token_acc.amount, 50_000_000 - 5_000_000 - 5_000_000 - 10_000_000);
+        assert_eq!(get_subscription(&svm, &sub_pda).redeemed_coupons, vec![coupon_pda]);
+
+        // "Changing" to the current plan can't be used to redeem the coupon again
+        let change_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(sub_pda, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new(coupon_pda, false),
+            ],
+            data: {
+                let mut data = get_discriminator("change_plan").to_vec();
+                data.extend_from_slice(&0u64.to_le_bytes()); // max_token_amount
+                data.push(0); // None chosen_amount
+                data
+            },
+        };
+        svm.expire_blockhash();
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[change_ix],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_private_offer() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (redemption_pda, _) = Pubkey::find_program_address(
+            &[b"offer", service_pda.as_ref(), &7u64.to_le_bytes()],
+            &PROGRAM_ID,
+        );
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // Negotiated deal: 4 USDC every two hours instead of 10 USDC hourly
+        let now = svm.get_sysvar::<Clock>().unix_timestamp;
+        let offer_terms = |amount: u64| {
+            let mut terms = service_pda.to_bytes().to_vec();
+            terms.extend_from_slice(plan_pda.as_ref());
+            terms.extend_from_slice(subscriber.pubkey().as_ref());
+            terms.extend_from_slice(&amount.to_le_bytes());
+            terms.extend_from_slice(&7200i64.to_le_bytes()); // interval
+            terms.extend_from_slice(&(now + 86_400).to_le_bytes()); // expires_at
+            terms.extend_from_slice(&7u64.to_le_bytes()); // nonce
+            terms
+        };
+        let sign_offer = |amount: u64| {
+            let mut message = crate::state::OFFER_DOMAIN.to_vec();
+            message.extend_from_slice(&offer_terms(amount));
+            let signature: [u8; 64] = merchant.sign_message(&message).as_ref().try_into().unwrap();
+            #[allow(deprecated)]
+            solana_sdk::ed25519_instruction::new_ed25519_instruction_with_signature(
+                &message,
+                &signature,
+                &merchant.pubkey().to_bytes(),
+            )
+        };
+
+        let subscribe = |svm: &mut LiteSVM, signed: u64, amount: u64| {
+            let mut data = get_discriminator("create_subscription").to_vec();
+            data.extend_from_slice(&0u64.to_le_bytes()); // max_token_amount
+            data.push(1); // Some offer
+            data.extend_from_slice(&offer_terms(amount));
+            data.push(0); // None chosen_amount
+            let sub_ix = Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(subscriber.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new_readonly(plan_pda, false),
+                    AccountMeta::new(sub_pda, false),
+                    AccountMeta::new(subscriber_token, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new(treasury, false),
+                    AccountMeta::new_readonly(spl_token::ID, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None revenue_split
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None price_feed
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None coupon
+                    AccountMeta::new_readonly(solana_sdk::sysvar::instructions::ID, false),
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None affiliate
+                    AccountMeta::new(redemption_pda, false),
+                ],
+                data,
+            };
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[sign_offer(signed), sub_ix],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        // Terms that differ from what the merchant signed are rejected
+        assert!(subscribe(&mut svm, 4_000_000, 1_000_000).is_err());
+        // So is a signed price that doesn't cover the 0.1 USDC crank reward
+        assert!(subscribe(&mut svm, 100_000, 100_000).is_err());
+        subscribe(&mut svm, 4_000_000, 4_000_000).expect("Create subscription failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 4_000_000);
+
+        // The negotiated interval is locked in
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 7201;
+        svm.set_sysvar::<Clock>(&clock);
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 4_000_000 + 3_900_000);
+
+        // Pay-what-you-want enabled later doesn't reopen the negotiated price
+        let mut range_data = get_discriminator("set_pay_what_you_want").to_vec();
+        range_data.push(1); // Some range
+        range_data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        range_data.extend_from_slice(&20_000_000u64.to_le_bytes());
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(merchant.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                ],
+                data: range_data,
+            }],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set pay-what-you-want failed");
+        let mut update_data = get_discriminator("update_chosen_amount").to_vec();
+        update_data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[Instruction {
+                    program_id: PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new_readonly(subscriber.pubkey(), true),
+                        AccountMeta::new_readonly(service_pda, false),
+                        AccountMeta::new_readonly(plan_pda, false),
+                        AccountMeta::new(sub_pda, false),
+                        AccountMeta::new(subscriber_token, false),
+                        AccountMeta::new_readonly(mint, false),
+                        AccountMeta::new_readonly(spl_token::ID, false),
+                    ],
+                    data: update_data,
+                }],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+        assert_eq!(get_subscription(&svm, &sub_pda).amount, 4_000_000);
+
+        // The offer can't be accepted again after cancelling
+        let cancel_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: get_discriminator("cancel_subscription").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[cancel_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Cancel failed");
+        assert!(subscribe(&mut svm, 4_000_000, 4_000_000).is_err());
+    }
+
+    #[test]
+    fn test_affiliate_rewards() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let referrer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+        let referrer_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+        setup_token_account(&mut svm, &referrer_token, &mint, &referrer, 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (affiliate_pda, _) = Pubkey::find_program_address(
+            &[b"affiliate", service_pda.as_ref(), referrer.as_ref()],
+            &PROGRAM_ID,
+        );
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 10% of the first collection
+        let mut register_data = get_discriminator("register_affiliate").to_vec();
+        register_data.extend_from_slice(&1_000u16.to_le_bytes());
+        register_data.extend_from_slice(&2u32.to_le_bytes());
+        let register_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(referrer, false),
+                AccountMeta::new_readonly(referrer_token, false),
+                AccountMeta::new(affiliate_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: register_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[register_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Register affiliate failed");
+
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None revenue_split
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None price_feed
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None coupon
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None instructions_sysvar
+                AccountMeta::new(affiliate_pda, false),
+            ],
+            data: create_subscription_data(0),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Create subscription failed");
+
+        let collect = |svm: &mut LiteSVM, elapsed: i64, with_affiliate: bool| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += elapsed;
+            svm.set_sysvar::<Clock>(&clock);
+            let approve_ix = spl_token::instruction::approve(
+                &spl_token::ID,
+                &subscriber_token,
+                &sub_pda,
+                &subscriber.pubkey(),
+                &[],
+                10_000_000,
+            )
+            .unwrap();
+            let mut collect_ix = collect_payment_ix(
+                &cranker,
+                &service_pda,
+                &sub_pda,
+                &plan_pda,
+                &subscriber_token,
+                &treasury,
+                &cranker_token,
+                &mint,
+            );
+            if with_affiliate {
+                collect_ix.accounts.extend([
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None revenue_split
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None reward_pool
+                    AccountMeta::new_readonly(PROGRAM_ID, false), // None price_feed
+                    AccountMeta::new(affiliate_pda, false),
+                    AccountMeta::new(referrer_token, false),
+                ]);
+            }
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[approve_ix, collect_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker, &subscriber],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        // The referral must be paid while it lasts
+        assert!(collect(&mut svm, 3601, false).is_err());
+        collect(&mut svm, 0, true).expect("Collect payment failed");
+
+        // A frozen payout account leaves the reward with the merchant rather
+        // than blocking the collection, and the payout isn't used up
+        let set_payout_state = |svm: &mut LiteSVM, state: spl_token::state::AccountState| {
+            let mut payout_account = svm.get_account(&referrer_token).unwrap();
+            let mut payout = TokenAccount::unpack(&payout_account.data).unwrap();
+            payout.state = state;
+            TokenAccount::pack(payout, &mut payout_account.data).unwrap();
+            svm.set_account(referrer_token, payout_account).unwrap();
+        };
+        set_payout_state(&mut svm, spl_token::state::AccountState::Frozen);
+        collect(&mut svm, 3601, true).expect("Collect payment failed");
+        set_payout_state(&mut svm, spl_token::state::AccountState::Initialized);
+        assert!(collect(&mut svm, 3601, false).is_err());
+
+        // A batch pays the referral too, given the affiliate and payout account
+        let approve_ix = spl_token::instruction::approve(
+            &spl_token::ID,
+            &subscriber_token,
+            &sub_pda,
+            &subscriber.pubkey(),
+            &[],
+            10_000_000,
+        )
+        .unwrap();
+        let batch_ix = collect_payments_batch_ix(
+            &cranker,
+            &service_pda,
+            &cranker_token,
+            &mint,
+            &spl_token::ID,
+            &[vec![
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new(affiliate_pda, false),
+                AccountMeta::new(referrer_token, false),
+            ]],
+        );
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[approve_ix, batch_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Batch collection with referral failed");
+
+        // After N = 2 paid collections the merchant keeps everything again
+        collect(&mut svm, 3601, false).expect("Collect payment failed");
+
+        let referrer_acc =
+            TokenAccount::unpack(&svm.get_account(&referrer_token).unwrap().data).unwrap();
+        assert_eq!(referrer_acc.amount, 2_000_000);
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(
+            treasury_acc.amount,
+            10_000_000 + 8_900_000 + 9_900_000 + 8_900_000 + 9_900_000
+        );
+
+        // referrals (u32) and total_rewards (u64) follow service, wallet,
+        // payout account, reward_bps and reward_cycles
+        let affiliate = svm.get_account(&affiliate_pda).unwrap();
+        let referrals = u32::from_le_bytes(affiliate.data[110..114].try_into().unwrap());
+        let total_rewards = u64::from_le_bytes(affiliate.data[114..122].try_into().unwrap());
+        assert_eq!(referrals, 1);
+        assert_eq!(total_rewards, 2_000_000);
+    }
+
+    #[test]
+    fn test_loyalty_tiers() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // 10% off once two payments have been made
+        let mut tiers_data = get_discriminator("set_loyalty_tiers").to_vec();
+        tiers_data.extend_from_slice(&1u32.to_le_bytes()); // one tier
+        tiers_data.extend_from_slice(&2u32.to_le_bytes()); // min_payments
+        tiers_data.extend_from_slice(&1_000u16.to_le_bytes()); // discount_bps
+        let tiers_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: tiers_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[tiers_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set loyalty tiers failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let collect = |svm: &mut LiteSVM| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += 3601;
+            svm.set_sysvar::<Clock>(&clock);
+            let approve_ix = spl_token::instruction::approve(
+                &spl_token::ID,
+                &subscriber_token,
+                &sub_pda,
+                &subscriber.pubkey(),
+                &[],
+                10_000_000,
+            )
+            .unwrap();
+            let collect_ix = collect_payment_ix(
+                &cranker,
+                &service_pda,
+                &sub_pda,
+                &plan_pda,
+                &subscriber_token,
+                &treasury,
+                &cranker_token,
+                &mint,
+            );
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[approve_ix, collect_ix],
+                Some(&cranker.pubkey()),
+                &[&cranker, &subscriber],
+                svm.latest_blockhash(),
+            ))
+            .expect("Collect payment failed");
+        };
+
+        // Second payment at full price, third at the loyalty price
+        collect(&mut svm);
+        collect(&mut svm);
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount, 10_000_000 + 9_900_000 + 8_900_000);
+    }
+
+    #[test]
+    fn test_price_phases() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        let phases_ix = |phases: &[(u64, u32)]| {
+            let mut phases_data = get_discriminator("set_price_phases").to_vec();
+            phases_data.extend_from_slice(&(phases.len() as u32).to_le_bytes());
+            for (amount, cycles) in phases {
+                phases_data.extend_from_slice(&amount.to_le_bytes());
+                phases_data.extend_from_slice(&3600i64.to_le_bytes());
+                phases_data.extend_from_slice(&cycles.to_le_bytes());
+            }
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(merchant.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                ],
+                data: phases_data,
+            }
+        };
+
+        // A phase at or below the 0.1 token crank reward is refused, even
+        // after the opening one
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[phases_ix(&[(5_000_000, 2), (100_000, 0)])],
+                Some(&merchant.pubkey()),
+                &[&merchant],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        // Two introductory payments at 5 tokens, then 10 tokens indefinitely
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[phases_ix(&[(5_000_000, 2), (10_000_000, 0)])],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set price phases failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        let advance = |svm: &mut LiteSVM| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += 3601;
+            svm.set_sysvar::<Clock>(&clock);
+            svm.expire_blockhash();
+        };
+
+        // Second introductory payment, approved at creation
+        advance(&mut svm);
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            std::slice::from_ref(&collect_ix),
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Introductory collection failed");
+
+        // The raise to 10 tokens is refused until the subscriber re-approves
+        advance(&mut svm);
+        let result = svm.send_transaction(Transaction::new_signed_with_payer(
+            std::slice::from_ref(&collect_ix),
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ));
+        assert!(result.is_err(), "Collection should need re-approval");
+
+        let reapprove_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: get_discriminator("reapprove_delegation").to_vec(),
+        };
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[reapprove_ix, collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collection after re-approval failed");
+
+        let treasury_acc = TokenAccount::unpack(&svm.get_account(&treasury).unwrap().data).unwrap();
+        let cranker_acc = TokenAccount::unpack(&svm.get_account(&cranker_token).unwrap().data).unwrap();
+        assert_eq!(treasury_acc.amount + cranker_acc.amount, 5_000_000 + 5_000_000 + 10_000_000);
+    }
+
+    #[test]
+    fn test_unapproved_phase_rise_goes_past_due() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // Two introductory payments at 5 tokens, then 10 tokens indefinitely
+        let mut phases_data = get_discriminator("set_price_phases").to_vec();
+        phases_data.extend_from_slice(&2u32.to_le_bytes()); // two phases
+        for (amount, cycles) in [(5_000_000u64, 2u32), (10_000_000, 0)] {
+            phases_data.extend_from_slice(&amount.to_le_bytes());
+            phases_data.extend_from_slice(&3600i64.to_le_bytes());
+            phases_data.extend_from_slice(&cycles.to_le_bytes());
+        }
+        let phases_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: phases_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[phases_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set price phases failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        let past_due_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(subscriber_token, false),
+                AccountMeta::new_readonly(PROGRAM_ID, false), // None reward_pool
+            ],
+            data: get_discriminator("mark_past_due").to_vec(),
+        };
+        let send = |svm: &mut LiteSVM, ix: &Instruction| {
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                std::slice::from_ref(ix),
+                Some(&cranker.pubkey()),
+                &[&cranker],
+                svm.latest_blockhash(),
+            ))
+        };
+        let advance = |svm: &mut LiteSVM| {
+            let mut clock = svm.get_sysvar::<Clock>();
+            clock.unix_timestamp += 3601;
+            svm.set_sysvar::<Clock>(&clock);
+        };
+
+        // The second introductory payment is funded and approved, so it
+        // can't be flagged
+        advance(&mut svm);
+        assert!(send(&mut svm, &past_due_ix).is_err());
+        send(&mut svm, &collect_ix).expect("Introductory collection failed");
+
+        // The subscriber never re-approves the raise to 10 tokens: the
+        // charge can't be collected, so the subscription goes past due
+        advance(&mut svm);
+        assert!(send(&mut svm, &collect_ix).is_err());
+        send(&mut svm, &past_due_ix).expect("Mark past due failed");
+        assert!(get_subscription(&svm, &sub_pda).status == SubscriptionStatus::PastDue);
+    }
+
+    #[test]
+    fn test_pay_what_you_want() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        let range_ix = |min_amount: u64, max_amount: u64| {
+            let mut range_data = get_discriminator("set_pay_what_you_want").to_vec();
+            range_data.push(1); // Some range
+            range_data.extend_from_slice(&min_amount.to_le_bytes());
+            range_data.extend_from_slice(&max_amount.to_le_bytes());
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(merchant.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new(plan_pda, false),
+                ],
+                data: range_data,
+            }
+        };
+
+        // A floor at or below the 0.1 token crank reward is refused
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[range_ix(100_000, 20_000_000)],
+                Some(&merchant.pubkey()),
+                &[&merchant],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        // Anything from 5 to 20 tokens per cycle
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[range_ix(5_000_000, 20_000_000)],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set pay-what-you-want failed");
+
+        // The range sets the amount, so update_plan can't
+        let mut update_data = get_discriminator("update_plan").to_vec();
+        update_data.push(1); // Some amount
+        update_data.extend_from_slice(&15_000_000u64.to_le_bytes());
+        update_data.extend_from_slice(&[0, 0, 0, 0]); // None for the rest
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: update_data,
+        };
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[update_ix],
+                Some(&merchant.pubkey()),
+                &[&merchant],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        let sub_ix = |chosen_amount: u64| {
+            let mut data = get_discriminator("create_subscription").to_vec();
+            data.extend_from_slice(&0u64.to_le_bytes()); // max_token_amount
+            data.push(0); // None offer
+            data.push(1); // Some chosen_amount
+            data.extend_from_slice(&chosen_amount.to_le_bytes());
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(subscriber.pubkey(), true),
+                    AccountMeta::new(service_pda, false),
+                    AccountMeta::new_readonly(plan_pda, false),
+                    AccountMeta::new(sub_pda, false),
+                    AccountMeta::new(subscriber_token, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new(treasury, false),
+                    AccountMeta::new_readonly(spl_token::ID, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                ],
+                data,
+            }
+        };
+
+        // Below the floor is refused
+        let result = svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix(3_000_000)],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ));
+        assert!(result.is_err(), "Amount below the floor should fail");
+
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix(8_000_000)],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Subscription at a chosen amount failed");
+
+        // Raise the amount to 12 tokens for the next cycle
+        let mut update_data = get_discriminator("update_chosen_amount").to_vec();
+        update_data.extend_from_slice(&12_000_000u64.to_le_bytes());
+        let update_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: update_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            std::slice::from_ref(&update_ix),
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Update chosen amount failed");
+
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let sub_acc = TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_acc.amount, 50_000_000 - 8_000_000 - 12_000_000);
+
+        // A past-due charge keeps the amount that was missed
+        let mut subscription = get_subscription(&svm, &sub_pda);
+        subscription.status = SubscriptionStatus::PastDue;
+        set_subscription(&mut svm, &sub_pda, &subscription);
+        svm.expire_blockhash();
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[update_ix],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_free_tier_upgrade() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (paid_plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (free_plan_pda, _) = get_plan_pda(&service_pda, 1);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &free_plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &paid_plan_pda, &mint, &treasury);
+
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&4u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Free");
+        plan_data.extend_from_slice(&0u64.to_le_bytes()); // amount
+        plan_data.extend_from_slice(&0u64.to_le_bytes()); // crank_reward
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+        let plan_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(free_plan_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: plan_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[plan_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Free plan creation failed");
+
+        // Free tiers can't carry an activation fee either
+        let mut fee_data = get_discriminator("set_setup_fee").to_vec();
+        fee_data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        let fee_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(free_plan_pda, false),
+            ],
+            data: fee_data,
+        };
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[fee_ix],
+                Some(&merchant.pubkey()),
+                &[&merchant],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+
+        // Subscribing to the free tier moves no tokens
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &free_plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+        let sub_acc = TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_acc.amount, 50_000_000);
+
+        // ... and never becomes due
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 10 * 3600;
+        svm.set_sysvar::<Clock>(&clock);
+        let collect_ix = |plan_pda: &Pubkey| {
+            collect_payment_ix(
+                &cranker,
+                &service_pda,
+                &sub_pda,
+                plan_pda,
+                &subscriber_token,
+                &treasury,
+                &cranker_token,
+                &mint,
+            )
+        };
+        let result = svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix(&free_plan_pda)],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ));
+        assert!(result.is_err(), "Free subscriptions should never be due");
+
+        // Upgrading to the paid plan makes the first charge due immediately
+        let change_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new_readonly(free_plan_pda, false),
+                AccountMeta::new_readonly(paid_plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(sub_pda, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: {
+                let mut data = get_discriminator("change_plan").to_vec();
+                data.extend_from_slice(&0u64.to_le_bytes()); // max_token_amount
+                data.push(0); // None chosen_amount
+                data
+            },
+        };
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[change_ix, collect_ix(&paid_plan_pda)],
+            Some(&cranker.pubkey()),
+            &[&cranker, &subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Upgrade and collection failed");
+
+        let sub_acc = TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_acc.amount, 50_000_000 - 10_000_000);
+    }
+
+    #[test]
+    fn test_seat_quantity_proration() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            100_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // Between 1 and 10 seats at 10 tokens each
+        let mut limits_data = get_discriminator("set_seat_limits").to_vec();
+        limits_data.extend_from_slice(&1u32.to_le_bytes());
+        limits_data.extend_from_slice(&10u32.to_le_bytes());
+        let limits_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(merchant.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(plan_pda, false),
+            ],
+            data: limits_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[limits_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Set seat limits failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let quantity_ix = |quantity: u32| {
+            let mut data = get_discriminator("update_quantity").to_vec();
+            data.extend_from_slice(&quantity.to_le_bytes());
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new_readonly(subscriber.pubkey(), true),
+                    AccountMeta::new_readonly(service_pda, false),
+                    AccountMeta::new_readonly(plan_pda, false),
+                    AccountMeta::new(sub_pda, false),
+                    AccountMeta::new(subscriber_token, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(spl_token::ID, false),
+                ],
+                data,
+            }
+        };
+
+        // More seats than the plan allows are refused
+        let result = svm.send_transaction(Transaction::new_signed_with_payer(
+            &[quantity_ix(11)],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ));
+        assert!(result.is_err(), "Quantity above the maximum should fail");
+
+        // Two seats added halfway through the period cost half a period each
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 1800;
+        svm.set_sysvar::<Clock>(&clock);
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[quantity_ix(3)],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Update quantity failed");
+
+        clock.unix_timestamp += 1801;
+        svm.set_sysvar::<Clock>(&clock);
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        // First seat up front, then 3 seats plus the 10-token proration
+        let sub_acc = TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_acc.amount, 100_000_000 - 10_000_000 - 30_000_000 - 10_000_000);
+    }
+
+    #[test]
+    fn test_team_seat_members() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let seat_pda = |member: &Pubkey| {
+            Pubkey::find_program_address(&[b"seat", sub_pda.as_ref(), member.as_ref()], &PROGRAM_ID).0
+        };
+        let member_ix = |name: &str, member: &Pubkey| {
+            let mut accounts = vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+            ];
+            if name == "add_member" {
+                accounts.push(AccountMeta::new_readonly(*member, false));
+                accounts.push(AccountMeta::new(seat_pda(member), false));
+                accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+            } else {
+                accounts.push(AccountMeta::new(seat_pda(member), false));
+            }
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts,
+                data: get_discriminator(name).to_vec(),
+            }
+        };
+        let send = |svm: &mut LiteSVM, ix: Instruction| {
+            svm.expire_blockhash();
+            svm.send_transaction(Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+        };
+
+        // The single seat goes to Alice, leaving none for Bob
+        send(&mut svm, member_ix("add_member", &alice)).expect("Add member failed");
+        assert!(
+            send(&mut svm, member_ix("add_member", &bob)).is_err(),
+            "Members beyond the seat count should be refused"
+        );
+
+        // Alice's access resolves to the owner's active subscription
+        let access_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(alice, false),
+                AccountMeta::new_readonly(sub_pda, false),
+                AccountMeta::new_readonly(seat_pda(&alice), false),
+            ],
+            data: get_discriminator("check_access").to_vec(),
+        };
+        send(&mut svm, access_ix).expect("Member access check failed");
+
+        // Freeing Alice's seat lets Bob take it
+        send(&mut svm, member_ix("remove_member", &alice)).expect("Remove member failed");
+        send(&mut svm, member_ix("add_member", &bob)).expect("Reassigning the seat failed");
+
+        // Cancelling must close every assigned seat along with the subscription
+        let cancel_ix = |seats: &[Pubkey]| {
+            let mut accounts = vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ];
+            accounts.extend(seats.iter().map(|seat| AccountMeta::new(*seat, false)));
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts,
+                data: get_discriminator("cancel_subscription").to_vec(),
+            }
+        };
+        assert!(
+            send(&mut svm, cancel_ix(&[])).is_err(),
+            "Cancelling with seats left open should be refused"
+        );
+        send(&mut svm, cancel_ix(&[seat_pda(&bob)])).expect("Cancel failed");
+        assert!(svm.get_account(&seat_pda(&bob)).is_none_or(|seat| seat.lamports == 0));
+
+        // A fresh subscription at the same address inherits no members
+        svm.expire_blockhash();
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+        let access_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(bob, false),
+                AccountMeta::new_readonly(sub_pda, false),
+                AccountMeta::new_readonly(seat_pda(&bob), false),
+            ],
+            data: get_discriminator("check_access").to_vec(),
+        };
+        assert!(send(&mut svm, access_ix).is_err());
+
+        // Expiry doesn't need every seat: Alice's is left behind, stale
+        send(&mut svm, member_ix("add_member", &alice)).expect("Add member failed");
+        let mut subscription = get_subscription(&svm, &sub_pda);
+        subscription.status = SubscriptionStatus::PastDue;
+        subscription.next_billing_timestamp = 0;
+        set_subscription(&mut svm, &sub_pda, &subscription);
+        let expire_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber.pubkey(), false),
+            ],
+            data: get_discriminator("expire_subscription").to_vec(),
+        };
+        send(&mut svm, expire_ix).expect("Expiry with a seat left open failed");
+        assert!(svm.get_account(&seat_pda(&alice)).is_some());
+
+        // Once resubscribed, the stale seat can be freed without touching the count
+        svm.expire_blockhash();
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+        send(&mut svm, member_ix("remove_member", &alice)).expect("Removing a stale seat failed");
+        assert_eq!(get_subscription(&svm, &sub_pda).assigned_seats, 0);
+    }
+
+    #[test]
+    fn test_subscription_add_on_items() {
+        let mut svm = LiteSVM::new();
+        let program_bytes = include_bytes!("../../../target/deploy/solbill.so");
+        let _ = svm.add_program(PROGRAM_ID, program_bytes);
+
+        let merchant = Keypair::new();
+        let subscriber = Keypair::new();
+        let cranker = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let subscriber_token = Pubkey::new_unique();
+        let cranker_token = Pubkey::new_unique();
+
+        svm.airdrop(&merchant.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&subscriber.pubkey(), LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&cranker.pubkey(), LAMPORTS_PER_SOL).unwrap();
+
+        setup_mint_and_accounts(
+            &mut svm,
+            &merchant,
+            &subscriber,
+            &mint,
+            &treasury,
+            &subscriber_token,
+            50_000_000,
+        );
+        setup_token_account(&mut svm, &cranker_token, &mint, &cranker.pubkey(), 0);
+
+        let (service_pda, _) = get_service_pda(&merchant.pubkey());
+        let (plan_pda, _) = get_plan_pda(&service_pda, 0);
+        let (addon_pda, _) = get_plan_pda(&service_pda, 1);
+        let (sub_pda, _) = get_subscription_pda(&subscriber.pubkey(), &plan_pda);
+        let (item_pda, _) = Pubkey::find_program_address(
+            &[b"item", sub_pda.as_ref(), addon_pda.as_ref()],
+            &PROGRAM_ID,
+        );
+
+        init_service_and_plan(&mut svm, &merchant, &service_pda, &plan_pda, &mint, &treasury);
+
+        // Extra storage at 3 tokens per cycle
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&7u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Storage");
+        plan_data.extend_from_slice(&3_000_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+        let plan_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(addon_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: plan_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[plan_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .expect("Add-on plan creation failed");
+
+        create_subscription_ix(&mut svm, &subscriber, &service_pda, &plan_pda, &sub_pda, &subscriber_token, &mint, &treasury);
+
+        let attach_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new_readonly(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new_readonly(plan_pda, false),
+                AccountMeta::new_readonly(addon_pda, false),
+                AccountMeta::new(item_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: get_discriminator("attach_item").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            std::slice::from_ref(&attach_ix),
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Attach item failed");
+
+        // The add-on rides along with the base plan on one charge
+        let mut clock = svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += 3601;
+        svm.set_sysvar::<Clock>(&clock);
+        let collect_ix = collect_payment_ix(
+            &cranker,
+            &service_pda,
+            &sub_pda,
+            &plan_pda,
+            &subscriber_token,
+            &treasury,
+            &cranker_token,
+            &mint,
+        );
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[collect_ix],
+            Some(&cranker.pubkey()),
+            &[&cranker],
+            svm.latest_blockhash(),
+        ))
+        .expect("Collect payment failed");
+
+        let sub_acc = TokenAccount::unpack(&svm.get_account(&subscriber_token).unwrap().data).unwrap();
+        assert_eq!(sub_acc.amount, 50_000_000 - 10_000_000 - 13_000_000);
+
+        // Detaching closes the item account
+        let detach_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(item_pda, false),
+            ],
+            data: get_discriminator("detach_item").to_vec(),
+        };
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[detach_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Detach item failed");
+        assert!(svm.get_account(&item_pda).is_none_or(|account| account.lamports == 0));
+
+        // Cancelling must close every attached add-on along with the subscription
+        svm.expire_blockhash();
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[attach_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Re-attach item failed");
+        let cancel_ix = |items: &[Pubkey]| {
+            let mut accounts = vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(service_pda, false),
+                AccountMeta::new(sub_pda, false),
+                AccountMeta::new(subscriber_token, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ];
+            accounts.extend(items.iter().map(|item| AccountMeta::new(*item, false)));
+            Instruction {
+                program_id: PROGRAM_ID,
+                accounts,
+                data: get_discriminator("cancel_subscription").to_vec(),
+            }
+        };
+        svm.expire_blockhash();
+        assert!(svm
+            .send_transaction(Transaction::new_signed_with_payer(
+                &[cancel_ix(&[])],
+                Some(&subscriber.pubkey()),
+                &[&subscriber],
+                svm.latest_blockhash(),
+            ))
+            .is_err());
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[cancel_ix(&[item_pda])],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .expect("Cancel failed");
+        assert!(svm.get_account(&item_pda).is_none_or(|account| account.lamports == 0));
+    }
+
+    /// Write a fully verified Pyth `PriceUpdateV2` account.
+    fn set_price_feed(svm: &mut LiteSVM, address: &Pubkey, price: i64, exponent: i32, publish_time: i64) {
+        let mut data = vec![34, 241, 35, 99, 157, 126, 244, 205]; // discriminator
+        data.extend_from_slice(&[0u8; 32]); // write_authority
+        data.push(1); // VerificationLevel::Full
+        data.extend_from_slice(&[0u8; 32]); // feed_id
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&(price as u64 / 1_000).to_le_bytes()); // conf: 0.1%
+        data.extend_from_slice(&exponent.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes());
+        data.extend_from_slice(&publish_time.to_le_bytes()); // prev_publish_time
+        data.extend_from_slice(&price.to_le_bytes()); // ema_price
+        data.extend_from_slice(&0u64.to_le_bytes()); // ema_conf
+        data.extend_from_slice(&0u64.to_le_bytes()); // posted_slot
+        svm.set_account(
+            *address,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data,
+                owner: crate::oracle::PYTH_RECEIVER_ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn setup_mint_and_treasury(
+        svm: &mut LiteSVM,
+        merchant: &Keypair,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+    ) {
+        let mut mint_data = vec![0u8; Mint::LEN];
+        Mint::pack(
+            Mint {
+                mint_authority: solana_sdk::program_option::COption::Some(merchant.pubkey()),
+                supply: 100_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: solana_sdk::program_option::COption::None,
+            },
+            &mut mint_data,
+        )
+        .unwrap();
+        svm.set_account(
+            *mint,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data: mint_data,
+                owner: spl_token::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let mut treasury_data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: *mint,
+                owner: merchant.pubkey(),
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            },
+            &mut treasury_data,
+        )
+        .unwrap();
+        svm.set_account(
+            *treasury,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data: treasury_data,
+                owner: spl_token::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn setup_mint_and_accounts(
+        svm: &mut LiteSVM,
+        merchant: &Keypair,
+        subscriber: &Keypair,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+        subscriber_token: &Pubkey,
+        sub_balance: u64,
+    ) {
+        setup_mint_and_treasury(svm, merchant, mint, treasury);
+        let mut sub_token_data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: *mint,
+                owner: subscriber.pubkey(),
+                amount: sub_balance,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            },
+            &mut sub_token_data,
+        )
+        .unwrap();
+        svm.set_account(
+            *subscriber_token,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data: sub_token_data,
+                owner: spl_token::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn setup_token_account(
+        svm: &mut LiteSVM,
+        address: &Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) {
+        let mut token_data = vec![0u8; TokenAccount::LEN];
+        TokenAccount::pack(
+            TokenAccount {
+                mint: *mint,
+                owner: *owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..TokenAccount::default()
+            },
+            &mut token_data,
+        )
+        .unwrap();
+        svm.set_account(
+            *address,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data: token_data,
+                owner: spl_token::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn setup_fee_mint(svm: &mut LiteSVM, mint: &Pubkey, authority: &Pubkey, fee_bps: u16) {
+        use anchor_spl::token_2022::spl_token_2022::{
+            extension::{
+                transfer_fee::{TransferFee, TransferFeeConfig},
+                BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+            },
+            solana_program::program_option::COption,
+            state::Mint as Mint2022,
+        };
+
+        let space =
+            ExtensionType::try_calculate_account_len::<Mint2022>(&[ExtensionType::TransferFeeConfig])
+                .unwrap();
+        let mut data = vec![0u8; space];
+        let mut state = StateWithExtensionsMut::<Mint2022>::unpack_uninitialized(&mut data).unwrap();
+        let fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: fee_bps.into(),
+        };
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = fee;
+        config.newer_transfer_fee = fee;
+        state.base = Mint2022 {
+            mint_authority: COption::Some(*authority),
+            supply: 100_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        svm.set_account(
+            *mint,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data,
+                owner: anchor_spl::token_2022::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn setup_hook_mint(svm: &mut LiteSVM, mint: &Pubkey, authority: &Pubkey, hook_program: &Pubkey) {
+        use anchor_spl::token_2022::spl_token_2022::{
+            extension::{
+                transfer_hook::TransferHook, BaseStateWithExtensionsMut, ExtensionType,
+                StateWithExtensionsMut,
+            },
+            solana_program::program_option::COption,
+            state::Mint as Mint2022,
+        };
+
+        let space =
+            ExtensionType::try_calculate_account_len::<Mint2022>(&[ExtensionType::TransferHook])
+                .unwrap();
+        let mut data = vec![0u8; space];
+        let mut state = StateWithExtensionsMut::<Mint2022>::unpack_uninitialized(&mut data).unwrap();
+        let hook = state.init_extension::<TransferHook>(true).unwrap();
+        hook.program_id = Some(*hook_program).try_into().unwrap();
+        state.base = Mint2022 {
+            mint_authority: COption::Some(*authority),
+            supply: 100_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        svm.set_account(
+            *mint,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data,
+                owner: anchor_spl::token_2022::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    /// Token-2022 account carrying the account-side extension its mint needs
+    /// (`TransferFeeAmount` or `TransferHookAccount`).
+    fn setup_token_2022_account(
+        svm: &mut LiteSVM,
+        address: &Pubkey,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+        extension: anchor_spl::token_2022::spl_token_2022::extension::ExtensionType,
+    ) {
+        use anchor_spl::token_2022::spl_token_2022::{
+            extension::{
+                transfer_fee::TransferFeeAmount, transfer_hook::TransferHookAccount,
+                BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+            },
+            state::{Account as Account2022, AccountState},
+        };
+
+        let space =
+            ExtensionType::try_calculate_account_len::<Account2022>(&[extension]).unwrap();
+        let mut data = vec![0u8; space];
+        let mut state =
+            StateWithExtensionsMut::<Account2022>::unpack_uninitialized(&mut data).unwrap();
+        match extension {
+            ExtensionType::TransferFeeAmount => {
+                state.init_extension::<TransferFeeAmount>(true).unwrap();
+            }
+            ExtensionType::TransferHookAccount => {
+                state.init_extension::<TransferHookAccount>(true).unwrap();
+            }
+            _ => unimplemented!("unsupported account extension"),
+        }
+        state.base = Account2022 {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        svm.set_account(
+            *address,
+            solana_sdk::account::Account {
+                lamports: 100_000_000,
+                data,
+                owner: anchor_spl::token_2022::ID,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    fn token_2022_balance(svm: &LiteSVM, address: &Pubkey) -> u64 {
+        use anchor_spl::token_2022::spl_token_2022::{
+            extension::StateWithExtensions, state::Account as Account2022,
+        };
+
+        let account = svm.get_account(address).unwrap();
+        StateWithExtensions::<Account2022>::unpack(&account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    fn init_service_and_plan(
+        svm: &mut LiteSVM,
+        merchant: &Keypair,
+        service_pda: &Pubkey,
+        plan_pda: &Pubkey,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+    ) {
+        init_service_and_plan_with_grace(svm, merchant, service_pda, plan_pda, mint, treasury, 3600);
+    }
+
+    fn init_service_and_plan_with_grace(
+        svm: &mut LiteSVM,
+        merchant: &Keypair,
+        service_pda: &Pubkey,
+        plan_pda: &Pubkey,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+        grace_period: i64,
+    ) {
+        let init_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(*service_pda, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(*treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: get_discriminator("initialize_service").to_vec(),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[init_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .unwrap();
+
+        let mut plan_data = get_discriminator("create_plan").to_vec();
+        plan_data.extend_from_slice(&8u32.to_le_bytes());
+        plan_data.extend_from_slice(b"Pro Plan");
+        plan_data.extend_from_slice(&10_000_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&100_000u64.to_le_bytes());
+        plan_data.extend_from_slice(&3600i64.to_le_bytes());
+        plan_data.extend_from_slice(&grace_period.to_le_bytes());
+        plan_data.extend_from_slice(&0u64.to_le_bytes());
+        plan_data.push(0); // None percentage_reward
+        plan_data.push(0); // native_sol false
+
+        let plan_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(merchant.pubkey(), true),
+                AccountMeta::new(*service_pda, false),
+                AccountMeta::new(*plan_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: plan_data,
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[plan_ix],
+            Some(&merchant.pubkey()),
+            &[&merchant],
+            svm.latest_blockhash(),
+        ))
+        .unwrap();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_subscription_ix(
+        svm: &mut LiteSVM,
+        subscriber: &Keypair,
+        service_pda: &Pubkey,
+        plan_pda: &Pubkey,
+        sub_pda: &Pubkey,
+        subscriber_token: &Pubkey,
+        mint: &Pubkey,
+        treasury: &Pubkey,
+    ) {
+        let sub_ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(subscriber.pubkey(), true),
+                AccountMeta::new(*service_pda, false),
+                AccountMeta::new_readonly(*plan_pda, false),
+                AccountMeta::new(*sub_pda, false),
+                AccountMeta::new(*subscriber_token, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(*treasury, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: create_subscription_data(0),
+        };
+        svm.send_transaction(Transaction::new_signed_with_payer(
+            &[sub_ix],
+            Some(&subscriber.pubkey()),
+            &[&subscriber],
+            svm.latest_blockhash(),
+        ))
+        .unwrap();
+    }
+
+    fn create_subscription_data(max_token_amount: u64) -> Vec<u8> {
+        let mut data = get_discriminator("create_subscription").to_vec();
+        data.extend_from_slice(&max_token_amount.to_le_bytes());
+        data.push(0); // None offer
+        data.push(0); // None chosen_amount
+        data
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn collect_payment_ix(
+        cranker: &Keypair,
+        service_pda: &Pubkey,
+        sub_pda: &Pubkey,
+        plan_pda: &Pubkey,
+        subscriber_token: &Pubkey,
+        treasury: &Pubkey,
+        cranker_token: &Pubkey,
+        mint: &Pubkey,
+    ) -> Instruction {
+        Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(cranker.pubkey(), true),
+                AccountMeta::new_readonly(*service_pda, false),
+                AccountMeta::new(*sub_pda, false),
+                AccountMeta::new_readonly(*plan_pda, false),
+                AccountMeta::new(*subscriber_token, false),
+                AccountMeta::new(*treasury, false),
+                AccountMeta::new(*cranker_token, false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+            ],
+            data: get_discriminator("collect_payment").to_vec(),
+        }
+    }
+
+    /// `collect_payments_batch` over `tuples`: each a (subscription, plan,
+    /// subscriber token account, treasury) tuple and its extra accounts.
+    fn collect_payments_batch_ix(
+        cranker: &Keypair,
+        service_pda: &Pubkey,
+        cranker_token: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+        tuples: &[Vec<AccountMeta>],
+    ) -> Instruction {
+        let (reward_vault, _) = Pubkey::find_program_address(
+            &[b"batch_rewards", cranker.pubkey().as_ref()],
+            &PROGRAM_ID,
+        );
+        let mut accounts = vec![
+            AccountMeta::new(cranker.pubkey(), true),
+            AccountMeta::new_readonly(*service_pda, false),
+            AccountMeta::new(*cranker_token, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(reward_vault, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(PROGRAM_ID, false), // No reward pool
+        ];
+        let mut data = get_discriminator("collect_payments_batch").to_vec();
+        data.extend_from_slice(&(tuples.len() as u32).to_le_bytes());
+        for tuple in tuples {
+            data.push((tuple.len() - 4) as u8); // extra accounts
+            accounts.extend_from_slice(tuple);
+        }
+        Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data,
+        }
     }
 
     #[test]
@@ -0,0 +1,43 @@
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+use anchor_lang::prelude::*;
+
+declare_id!("6Bv9XZ6GUmtDDhC7KqXzhdjgH9d33CQKvf2MNtn6K99v");
+
+/// `spl-transfer-hook-interface:execute` instruction discriminator.
+pub const EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+
+/// Test-only transfer hook: counts every transfer of its mint in a counter
+/// account listed as the single extra account meta.
+#[program]
+pub mod mock_transfer_hook {
+    use super::*;
+
+    #[instruction(discriminator = &EXECUTE_DISCRIMINATOR)]
+    pub fn execute(ctx: Context<Execute>, _amount: u64) -> Result<()> {
+        let mut data = ctx.accounts.counter.try_borrow_mut_data()?;
+        let count = u64::from_le_bytes(data[..8].try_into().unwrap());
+        let count = count
+            .checked_add(1)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        data[..8].copy_from_slice(&count.to_le_bytes());
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    /// CHECK: Source token account.
+    pub source: UncheckedAccount<'info>,
+    /// CHECK: Mint with the transfer hook.
+    pub mint: UncheckedAccount<'info>,
+    /// CHECK: Destination token account.
+    pub destination: UncheckedAccount<'info>,
+    /// CHECK: Transfer authority.
+    pub authority: UncheckedAccount<'info>,
+    /// CHECK: `["extra-account-metas", mint]` validation account.
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    /// CHECK: Counter account owned by this program (8-byte LE u64).
+    #[account(mut, owner = crate::ID)]
+    pub counter: UncheckedAccount<'info>,
+}